@@ -0,0 +1,74 @@
+use chip_8_emu::{ChipEmulator, ChipEmulatorConfig};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Draw 15 rows of the font in a tight loop with the sprite left edge at V0,
+/// the draws toggle the same pixels on and off
+const DRAW_LOOP: [u8; 14] = [
+    0x60, 0x00, // LD V0, x
+    0xA0, 0x50, // LD I, 0x050
+    0xD0, 0x1F, // DRW V0, V1, 15
+    0xD0, 0x1F, // DRW V0, V1, 15
+    0xD0, 0x1F, // DRW V0, V1, 15
+    0xD0, 0x1F, // DRW V0, V1, 15
+    0x12, 0x04, // JP 0x204
+];
+
+/// Draw loop instructions run by every benchmark iteration
+const CYCLES: u32 = 10_000;
+
+/// Create an emulator running the draw loop with the sprite at the given column
+fn draw_emulator(x: u8, packed_video: bool) -> ChipEmulator {
+    let mut rom = DRAW_LOOP;
+    rom[1] = x;
+
+    let config = ChipEmulatorConfig { packed_video, ..Default::default() };
+    let mut emulator = ChipEmulator::initialize(config).unwrap();
+    emulator.load_rom_bytes(&rom).unwrap();
+
+    emulator
+}
+
+/// Compare the fast path of the sprites fully on screen with the wrapping
+/// path of the sprites crossing the right edge, on the same video buffer
+fn draw_paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+
+    for (name, x) in [("fast path", 10), ("wrapping path", 60)] {
+        let mut emulator = draw_emulator(x, false);
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..CYCLES {
+                    emulator.step().unwrap();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Compare the video buffer footprint of both storages
+/// and the cost of returning the frame to the display
+fn video_buffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("video buffer");
+
+    for (name, packed_video) in [("bytes", false), ("packed", true)] {
+        let mut emulator = draw_emulator(10, packed_video);
+        for _ in 0..CYCLES {
+            emulator.step().unwrap();
+        }
+
+        // Criterion only measure the time, report the footprint alongside
+        println!("video buffer {}: {} bytes", name, emulator.video_buffer_footprint());
+
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(emulator.get_video_buffer().0.len()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, draw_paths, video_buffer);
+criterion_main!(benches);
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::error::AsmError;
+use crate::opcodes::{self, OpcodeInfo};
+use crate::{ChipEmulator, ROM_ADDRESS};
+
+/// Line of the source waiting for the labels to be resolved
+enum Item<'a> {
+    Instruction {
+        info: &'static OpcodeInfo,
+        operands: Vec<&'a str>,
+    },
+    Data(Vec<u8>),
+}
+
+impl Item<'_> {
+    /// Number of bytes of the assembled item
+    fn len(&self) -> usize {
+        match self {
+            Item::Instruction { info, .. } if is_long(info.mnemonic) => 4,
+            Item::Instruction { .. } => 2,
+            Item::Data(bytes) => bytes.len(),
+        }
+    }
+}
+
+/// Assemble a source written with the mnemonics of the opcodes table into a rom
+///
+/// Every line hold an optional `label:`, then an instruction or a `db` directive
+/// followed by comma separated bytes, `;` start a comment
+/// Numbers are decimal or prefixed with `0x`, `#` or `0b`, the labels can be
+/// used for the addresses and are resolved from the rom start address
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut address = ROM_ADDRESS as u32;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+
+        // Split the line without its comment
+        let code = line.split(';').next().unwrap_or_default();
+        let mut tokens = split_tokens(code);
+
+        // Define the labels at the start of the line
+        while let Some(label) = tokens.first().and_then(|token| token.strip_suffix(':')) {
+            if !is_identifier(label) {
+                return Err(AsmError::InvalidOperand { line: line_number });
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(AsmError::DuplicateLabel { line: line_number, label: label.to_string() });
+            }
+
+            tokens.remove(0);
+        }
+
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let item = if tokens[0].eq_ignore_ascii_case("db") {
+            let bytes = tokens[1..]
+                .iter()
+                .map(|token| parse_number(token).and_then(|value| u8::try_from(value).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or(AsmError::InvalidOperand { line: line_number })?;
+
+            Item::Data(bytes)
+        } else {
+            let info = opcodes::supported_opcodes()
+                .iter()
+                .find(|info| matches_mnemonic(info.mnemonic, &tokens))
+                .ok_or(AsmError::UnknownInstruction { line: line_number })?;
+
+            Item::Instruction { info, operands: tokens }
+        };
+
+        address += item.len() as u32;
+        items.push((line_number, item));
+    }
+
+    // Encode the items now that every label is known
+    let mut rom = Vec::with_capacity((address as usize).saturating_sub(ROM_ADDRESS));
+    for (line, item) in items {
+        match item {
+            Item::Data(bytes) => rom.extend_from_slice(&bytes),
+            Item::Instruction { info, operands } => {
+                encode(info, &operands, &labels, line, &mut rom)?;
+            }
+        }
+    }
+
+    Ok(rom)
+}
+
+/// Split a line in tokens separated by spaces or commas
+fn split_tokens(code: &str) -> Vec<&str> {
+    code.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+/// Return true if the mnemonic is the F000 NNNN instruction,
+/// followed by a 16 bit address operand
+fn is_long(mnemonic: &str) -> bool {
+    mnemonic.ends_with("long")
+}
+
+/// Return true if the line tokens match the mnemonic of the opcodes table
+fn matches_mnemonic(mnemonic: &str, tokens: &[&str]) -> bool {
+    let template = split_tokens(mnemonic);
+    let long = is_long(mnemonic);
+
+    if tokens.len() != template.len() + long as usize {
+        return false;
+    }
+
+    let long_operand = !long || is_address(tokens[tokens.len() - 1]);
+    long_operand && template.iter().zip(tokens).all(|(expected, token)| match *expected {
+        "Vx" | "Vy" => parse_register(token).is_some(),
+        "byte" => parse_number(token).is_some_and(|value| value <= 0xFF),
+        "nibble" => parse_number(token).is_some_and(|value| value <= 0xF),
+        "plane" => parse_number(token).is_some_and(|value| value <= 0b11),
+        "addr" => is_address(token),
+        literal => literal.eq_ignore_ascii_case(token),
+    })
+}
+
+/// Encode the instruction matching the given mnemonic at the end of the rom
+fn encode(
+    info: &OpcodeInfo,
+    operands: &[&str],
+    labels: &HashMap<&str, u32>,
+    line: usize,
+    rom: &mut Vec<u8>,
+) -> Result<(), AsmError> {
+    let mut opcode = info.value;
+
+    for (expected, token) in split_tokens(info.mnemonic).iter().zip(operands) {
+        let operand = match *expected {
+            "Vx" => (parse_register(token).unwrap_or(0) as u16) << 8,
+            "Vy" => (parse_register(token).unwrap_or(0) as u16) << 4,
+            "plane" => (parse_number(token).unwrap_or(0) as u16) << 8,
+            "byte" | "nibble" => parse_number(token).unwrap_or(0) as u16,
+            "addr" => {
+                let address = resolve(token, labels, line)?;
+                u16::try_from(address)
+                    .ok()
+                    .filter(|address| *address <= 0xFFF)
+                    .ok_or(AsmError::InvalidOperand { line })?
+            }
+            _ => 0,
+        };
+
+        opcode |= operand;
+    }
+    rom.extend_from_slice(&opcode.to_be_bytes());
+
+    // The long index load is followed by its 16 bit address
+    if is_long(info.mnemonic) {
+        let address = resolve(operands[operands.len() - 1], labels, line)?;
+        let address = u16::try_from(address).map_err(|_| AsmError::InvalidOperand { line })?;
+
+        rom.extend_from_slice(&address.to_be_bytes());
+    }
+
+    Ok(())
+}
+
+/// Return the value of a number or label operand
+fn resolve(token: &str, labels: &HashMap<&str, u32>, line: usize) -> Result<u32, AsmError> {
+    parse_number(token)
+        .or_else(|| labels.get(token).copied())
+        .ok_or_else(|| AsmError::UndefinedLabel { line, label: token.to_string() })
+}
+
+/// Return true if the token can be used as an address, a number or a label
+fn is_address(token: &str) -> bool {
+    parse_number(token).is_some() || is_identifier(token)
+}
+
+/// Return true if the token is a valid label name
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    let first = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+
+    first && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && parse_register(token).is_none()
+}
+
+/// Parse a V0 to VF register name
+fn parse_register(token: &str) -> Option<u8> {
+    let digit = token.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+
+    u8::from_str_radix(digit, 16).ok()
+}
+
+/// Parse a decimal, hexadecimal or binary number
+fn parse_number(token: &str) -> Option<u32> {
+    if let Some(hex) = token.strip_prefix("0x").or(token.strip_prefix("0X")).or(token.strip_prefix('#')) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = token.strip_prefix("0b") {
+        u32::from_str_radix(binary, 2).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+// Implement source loading for the emulator
+impl ChipEmulator {
+    /// Assemble the given source and load the resulting rom
+    pub fn load_source(&mut self, source: &str) -> Result<(), AsmError> {
+        let rom = assemble(source)?;
+        self.load_rom_bytes(&rom)?;
+
+        Ok(())
+    }
+}
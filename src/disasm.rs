@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use crate::opcodes;
+use crate::ChipInstruction;
+
+/// Disassembled word of a rom
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembledInstruction {
+    /// Address of the word
+    pub address: u16,
+    /// Raw bytes of the word
+    pub raw: [u8; 2],
+    /// Text of the instruction, the jump and call targets inside of the
+    /// disassembled bytes are replaced by their label
+    pub text: String,
+    /// Label of the word if it's the target of a jump or call
+    pub label: Option<String>,
+}
+
+/// Disassemble the given bytes, two at a time, the first word being
+/// at the given base address
+/// The words not matching a supported opcode are rendered as data with DB,
+/// the targets of the 1NNN and 2NNN instructions are labelled
+pub fn disassemble(bytes: &[u8], base_address: u16) -> Vec<DisassembledInstruction> {
+    let words: Vec<(u16, [u8; 2])> = bytes
+        .chunks(2)
+        .enumerate()
+        .map(|(i, word)| {
+            // An odd trailing byte is padded with zero
+            let raw = [word[0], word.get(1).copied().unwrap_or(0)];
+            let address = base_address.wrapping_add(i as u16 * 2);
+
+            (address, raw)
+        })
+        .collect();
+
+    // Collect the jump and call targets inside of the disassembled words
+    let in_range = |address: u16| words.iter().any(|(word_address, _)| *word_address == address);
+    let targets: HashSet<u16> = words
+        .iter()
+        .filter_map(|(_, raw)| jump_target(*raw))
+        .filter(|target| in_range(*target))
+        .collect();
+
+    words
+        .iter()
+        .map(|(address, raw)| {
+            let text = match jump_target(*raw) {
+                Some(target) if targets.contains(&target) => {
+                    let mnemonic = if raw[0] >> 4 == 0x01 { "JP" } else { "CALL" };
+                    format!("{} {}", mnemonic, label(target))
+                }
+                _ => disassemble_instruction(*raw),
+            };
+
+            DisassembledInstruction {
+                address: *address,
+                raw: *raw,
+                text,
+                label: targets.contains(address).then(|| label(*address)),
+            }
+        })
+        .collect()
+}
+
+/// Return the target of a 1NNN jump or 2NNN call
+fn jump_target(raw: [u8; 2]) -> Option<u16> {
+    match raw[0] >> 4 {
+        0x01 | 0x02 => Some(u16::from_be_bytes(raw) & 0x0FFF),
+        _ => None,
+    }
+}
+
+/// Return the label of the given address
+fn label(address: u16) -> String {
+    format!("L{:03X}", address)
+}
+
+/// Render a single instruction, the operands of the mnemonic are
+/// replaced by the instruction parameters
+pub fn disassemble_instruction(raw: [u8; 2]) -> String {
+    let instruction = ChipInstruction::new(raw);
+
+    let Some(info) = opcodes::lookup(u16::from_be_bytes(raw)) else {
+        return format!("DB 0x{:04X}", u16::from_be_bytes(raw));
+    };
+
+    info.mnemonic
+        .split(' ')
+        .map(|token| {
+            // Keep the separator following the operand
+            let (operand, separator) = match token.strip_suffix(',') {
+                Some(operand) => (operand, ","),
+                None => (token, ""),
+            };
+
+            let operand = match operand {
+                "Vx" => format!("V{:X}", instruction.x()),
+                "Vy" => format!("V{:X}", instruction.y()),
+                "byte" => format!("0x{:02X}", instruction.nn()),
+                "addr" => format!("0x{:03X}", instruction.nnn()),
+                "nibble" => instruction.n().to_string(),
+                "plane" => instruction.x().to_string(),
+                _ => operand.to_string(),
+            };
+
+            operand + separator
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
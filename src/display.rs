@@ -1,9 +1,74 @@
 #![allow(dead_code)]
-use sdl2::{Sdl, video::{Window, WindowContext}, render::{Canvas, TextureCreator}, pixels::PixelFormatEnum, rect::Rect};
+use sdl2::{Sdl, video::{Window, WindowContext}, render::{BlendMode, Canvas, Texture, TextureCreator}, pixels::{Color, PixelFormatEnum}, rect::Rect};
 
 pub const SCREEN_WIDTH: u32 = 64;
 pub const SCREEN_HEIGHT: u32 = 32;
 
+/// SUPER-CHIP high resolution screen size
+pub const HIRES_SCREEN_WIDTH: u32 = 128;
+pub const HIRES_SCREEN_HEIGHT: u32 = 64;
+
+/// Return the screen size matching the length of a video buffer
+pub fn screen_size_from_len(len: usize) -> (u32, u32) {
+    if len == (HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT) as usize {
+        (HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+    } else {
+        (SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+}
+
+/// Default BGRA colors of the pixels on the XO-CHIP second plane
+/// and on both planes
+const XO_CHIP_PLANE_COLOR: [u8; 4] = [0x00, 0x80, 0xFF, 0xFF];
+const XO_CHIP_OVERLAP_COLOR: [u8; 4] = [0x80, 0x80, 0x80, 0xFF];
+
+/// Renderer of the chip-8 video buffer
+pub trait ChipDisplay {
+    /// Draw the given chip-8 video buffer,
+    /// the resolution is deduced from the buffer length
+    fn update(&mut self, buffer: &[u8]);
+
+    /// Adapt the output to the given window size
+    fn resize(&mut self, size: (u32, u32));
+
+    /// Turn off every pixel of the output
+    fn clear(&mut self);
+
+    /// Present the output if a change since the last presentation was deferred
+    /// Should be called once per frame, ignored by default
+    fn present_pending(&mut self) {}
+
+    /// Show or hide the overlay drawn while the emulation is paused
+    /// Ignored by default
+    fn set_paused_overlay(&mut self, _paused: bool) {}
+
+    /// Return true while the output is animating, update must then be called
+    /// every frame even if the video buffer didn't change
+    /// Always false by default
+    fn is_fading(&self) -> bool {
+        false
+    }
+}
+
+/*
+*
+*   Null display Implementation
+*
+*/
+
+/// Display discarding every frame, for headless runs
+#[derive(Default)]
+pub struct NullDisplay;
+
+// Implement Chip Display for null display
+impl ChipDisplay for NullDisplay {
+    fn update(&mut self, _buffer: &[u8]) {}
+
+    fn resize(&mut self, _size: (u32, u32)) {}
+
+    fn clear(&mut self) {}
+}
+
 /*
 *
 *   Console based display Implementation
@@ -14,15 +79,18 @@ pub const SCREEN_HEIGHT: u32 = 32;
 pub struct ConsoleDisplay;
 
 // Implement Chip Display for console display
-impl ConsoleDisplay {
-    /// Draw the given chip-8 video buffer to the console
-    fn update(&self, video_buffer: &[u8; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize]) {
+impl ChipDisplay for ConsoleDisplay {
+    /// Draw the given chip-8 video buffer to the console,
+    /// the resolution is deduced from the buffer length
+    fn update(&mut self, video_buffer: &[u8]) {
         const PIXEL_ON: &str = "▓▓";
         const PIXEL_OFF: &str = "  ";
 
+        let (width, _) = screen_size_from_len(video_buffer.len());
+
         for (i, v) in video_buffer.iter().enumerate() {
             // New line if a row was printed
-            if i % SCREEN_WIDTH as usize == 0 {
+            if i % width as usize == 0 {
                 println!();
             }
 
@@ -37,6 +105,14 @@ impl ConsoleDisplay {
         // New ending line
         println!();
     }
+
+    /// The console output doesn't depend on the window size
+    fn resize(&mut self, _size: (u32, u32)) {}
+
+    /// Clear the console and move the cursor to the top left corner
+    fn clear(&mut self) {
+        print!("\x1B[2J\x1B[H");
+    }
 }
 
 /*
@@ -49,21 +125,53 @@ pub struct SdlDisplay {
     canvas: Canvas<Window>,
 
     texture_creator: TextureCreator<WindowContext>,
-    texture_buffer: [u8; (SCREEN_HEIGHT * SCREEN_WIDTH) as usize * 4],
+    /// Streaming texture reused every frame,
+    /// recreated only when the resolution change
+    texture: Texture,
+    texture_size: (u32, u32),
+    /// Sized for the high resolution mode, only the part
+    /// matching the texture size is used
+    texture_buffer: [u8; (HIRES_SCREEN_HEIGHT * HIRES_SCREEN_WIDTH) as usize * 4],
 
     output_rect: Rect,
-
-    /// On color at index: 1,
-    /// Off color at index: 0
-    pixel_color: [[u8; 4]; 2],
+    /// Latest window size received by resize
+    window_size: (u32, u32),
+    /// Set when the output rect changed since the last presentation
+    present_pending: bool,
+
+    /// Color of every pixel value, off color at index 0, on color at index 1,
+    /// XO-CHIP second plane color at index 2 and both planes color at index 3
+    pixel_color: [[u8; 4]; 4],
+
+    /// Draw a grid at the chip-8 pixels boundaries if true
+    grid: bool,
+    /// Tint the output to show that the emulation is paused if true
+    paused_overlay: bool,
+
+    /// Number of frames a pixel turned off take to fade to the off color,
+    /// 0 to turn it off immediately
+    fade: u8,
+    /// Remaining fade frames of every pixel
+    fade_levels: [u8; (HIRES_SCREEN_HEIGHT * HIRES_SCREEN_WIDTH) as usize],
 }
 
 impl SdlDisplay {
     /// Create the display object from a given sdl contex
     /// Take the on and off color in BGRA format
     pub fn new(contex: &Sdl, on_color: [u8; 4], off_color: [u8; 4]) -> Result<Self, String> {
+        Self::with_window_size(contex, (800, 600), on_color, off_color)
+    }
+
+    /// Create the display object with a window of the given initial size
+    /// Take the on and off color in BGRA format
+    pub fn with_window_size(
+        contex: &Sdl,
+        window_size: (u32, u32),
+        on_color: [u8; 4],
+        off_color: [u8; 4],
+    ) -> Result<Self, String> {
         let sdl_video = contex.video()?;
-        let window = sdl_video.window("Chip-8 emulator", 800, 600)
+        let window = sdl_video.window("Chip-8 emulator", window_size.0, window_size.1)
         .resizable()
         .opengl()
         .build()
@@ -74,18 +182,31 @@ impl SdlDisplay {
         let canvas_size = canvas.output_size().unwrap();
 
         let texture_creator = canvas.texture_creator();
+        let texture_size = (SCREEN_WIDTH, SCREEN_HEIGHT);
+        let texture = Self::create_texture(&texture_creator, texture_size)?;
 
-        let texture_buffer = [0xFF; (SCREEN_HEIGHT * SCREEN_WIDTH) as usize * 4];
+        let texture_buffer = [0xFF; (HIRES_SCREEN_HEIGHT * HIRES_SCREEN_WIDTH) as usize * 4];
 
         // Create and output the display object
         let mut display = Self {
             canvas,
 
             texture_creator,
+            texture,
+            texture_size,
             texture_buffer,
 
             output_rect: Rect::new(0, 0, 1, 1),
-            pixel_color: [off_color, on_color],
+            window_size: canvas_size,
+            present_pending: false,
+
+            pixel_color: [off_color, on_color, XO_CHIP_PLANE_COLOR, XO_CHIP_OVERLAP_COLOR],
+
+            grid: false,
+            paused_overlay: false,
+
+            fade: 0,
+            fade_levels: [0; (HIRES_SCREEN_HEIGHT * HIRES_SCREEN_WIDTH) as usize],
         };
 
         // Generate output rect
@@ -97,8 +218,167 @@ impl SdlDisplay {
         Ok(display)
     }
 
+    /// Enable or disable the debug grid overlay drawn at the pixels boundaries
+    /// The grid is only drawn on the window, the video buffer is not affected
+    pub fn set_grid(&mut self, enabled: bool) {
+        self.grid = enabled;
+        self.present_pending = true;
+    }
+
+    /// Set the BGRA colors of the four XO-CHIP pixel values: off,
+    /// first plane, second plane and both planes
+    pub fn set_palette(&mut self, palette: [[u8; 4]; 4]) {
+        self.pixel_color = palette;
+    }
+
+    /// Set the number of frames a pixel turned off take to fade to the off color,
+    /// reducing the flicker of the xor drawn sprites, 0 disable the fading
+    pub fn set_fade(&mut self, frames: u8) {
+        self.fade = frames;
+        self.fade_levels.fill(0);
+    }
+
+    /// Return the color of a pixel turned off with the given remaining fade frames
+    fn fade_color(&self, level: u8) -> [u8; 4] {
+        let [off, on, ..] = self.pixel_color;
+        let weight = level as u32;
+        let total = self.fade as u32 + 1;
+
+        let mut color = [0; 4];
+        for (i, channel) in color.iter_mut().enumerate() {
+            *channel = ((on[i] as u32 * weight + off[i] as u32 * (total - weight)) / total) as u8;
+        }
+
+        color
+    }
+
+    /// Darken the output rect and draw a pause symbol in its center
+    fn draw_paused_overlay(&mut self) {
+        const TINT_COLOR: Color = Color::RGBA(0x00, 0x00, 0x00, 0x90);
+        const SYMBOL_COLOR: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0xC0);
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(TINT_COLOR);
+        self.canvas.fill_rect(self.output_rect).unwrap();
+
+        // Two vertical bars sized from the output height
+        let rect = self.output_rect;
+        let bar_height = rect.height() / 4;
+        let bar_width = (bar_height / 3).max(1);
+        let top = rect.center().y() - bar_height as i32 / 2;
+
+        self.canvas.set_draw_color(SYMBOL_COLOR);
+        for offset in [-(bar_width as i32) * 3 / 2, bar_width as i32 / 2] {
+            let bar = Rect::new(rect.center().x() + offset, top, bar_width, bar_height);
+            self.canvas.fill_rect(bar).unwrap();
+        }
+    }
+
+    /// Draw a faint line at every chip-8 pixel boundary of the output rect
+    fn draw_grid(&mut self) {
+        const GRID_COLOR: Color = Color::RGBA(0x80, 0x80, 0x80, 0x40);
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(GRID_COLOR);
+
+        let rect = self.output_rect;
+        let (width, height) = self.texture_size;
+
+        // Vertical lines
+        for i in 0..=width {
+            let x = rect.x() + (i * rect.width() / width) as i32;
+            self.canvas.draw_line((x, rect.top()), (x, rect.bottom())).unwrap();
+        }
+
+        // Horizontal lines
+        for i in 0..=height {
+            let y = rect.y() + (i * rect.height() / height) as i32;
+            self.canvas.draw_line((rect.left(), y), (rect.right(), y)).unwrap();
+        }
+    }
+
+    /// Return the latest window size received by resize
+    pub fn window_size(&self) -> (u32, u32) {
+        self.window_size
+    }
+
+    /// Create a streaming ARGB8888 texture of the given size
+    fn create_texture(
+        texture_creator: &TextureCreator<WindowContext>,
+        size: (u32, u32),
+    ) -> Result<Texture, String> {
+        texture_creator
+            .create_texture_streaming(PixelFormatEnum::ARGB8888, size.0, size.1)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Recreate the streaming texture if its size differ from the given one
+    fn resize_texture(&mut self, size: (u32, u32)) {
+        if self.texture_size == size {
+            return;
+        }
+
+        let texture = Self::create_texture(&self.texture_creator, size).unwrap();
+        let old_texture = std::mem::replace(&mut self.texture, texture);
+        self.texture_size = size;
+
+        // Safety: the canvas and texture creator owning the texture are still alive
+        unsafe { old_texture.destroy() };
+    }
+
+    /// Present the texture_buffer to the screen
+    fn present_buffer(&mut self) {
+        // The buffer is now up to date
+        self.present_pending = false;
+
+        // Write the buffer on the texture, the pitch is the size of a row in bytes
+        let pitch = self.texture_size.0 as usize * 4;
+        let buffer_len = pitch * self.texture_size.1 as usize;
+        self.texture.update(None, &self.texture_buffer[..buffer_len], pitch).unwrap();
+        self.canvas.copy(&self.texture, None, self.output_rect).unwrap();
+
+        // Draw the grid overlay on top of the texture
+        if self.grid {
+            self.draw_grid();
+        }
+
+        // Draw the paused tint on top of everything
+        if self.paused_overlay {
+            self.draw_paused_overlay();
+        }
+
+        // Present the texture on the screen 
+        self.canvas.present();
+    }
+}
+
+// Implement Chip Display for sdl display
+impl ChipDisplay for SdlDisplay {
+    /// Present the texture buffer if a resize occurred since the last presentation
+    fn present_pending(&mut self) {
+        if self.present_pending {
+            self.present_buffer();
+        }
+    }
+
+    /// Show or hide the tint drawn over the output while the emulation is paused
+    fn set_paused_overlay(&mut self, paused: bool) {
+        self.paused_overlay = paused;
+        self.present_pending = true;
+    }
+
+    /// Return true while some pixels are fading
+    fn is_fading(&self) -> bool {
+        self.fade_levels.iter().any(|level| *level != 0)
+    }
+
     /// Generate output rect from the window size
-    pub fn resize(&mut self, window_size: (u32, u32)) {
+    /// The presentation is deferred to the next call to update or present_pending
+    /// so that a burst of resize events only present the buffer once
+    fn resize(&mut self, window_size: (u32, u32)) {
+        // Cache the latest window size
+        self.window_size = window_size;
+
         // Calculate the texture dimensions
         let aspect_ratio = SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32;
 
@@ -122,32 +402,45 @@ impl SdlDisplay {
         // Set the output rect 
         self.output_rect = rect;
 
-        // Present the texture buffer
-        self.present_buffer();
+        // Defer the presentation to the end of the frame
+        self.present_pending = true;
     }
 
-    /// Present the texture_buffer to the screen
-    fn present_buffer(&mut self) {
-        // Create the texture and write the buffer on it 
-        let mut texture = self.texture_creator.create_texture_static(PixelFormatEnum::ARGB8888, SCREEN_WIDTH, SCREEN_HEIGHT).unwrap();
-        texture.update(None, &self.texture_buffer, SCREEN_WIDTH as usize * 4).unwrap();
-        self.canvas.copy(&texture, None, self.output_rect).unwrap();
-
-        // Present the texture on the screen 
-        self.canvas.present();
-    }
-
-    /// Update the display with the given chip-8 video buffer
-    pub fn update(&mut self, video_buffer: &[u8]) {
+    /// Update the display with the given chip-8 video buffer,
+    /// the resolution is deduced from the buffer length
+    fn update(&mut self, video_buffer: &[u8]) {
         // Set the pixel color in the texture buffer to the on color
         // if the video buffer pixel is active
         //let mut buffer = self.texture_buffer.borrow_mut();
+        self.resize_texture(screen_size_from_len(video_buffer.len()));
         for (i, pixel) in video_buffer.iter().enumerate() {
-            self.texture_buffer[i*4..i*4 + 4].copy_from_slice(&self.pixel_color[*pixel as usize]);
+            let color = if self.fade == 0 {
+                self.pixel_color[*pixel as usize & 0b11]
+            } else if *pixel != 0 {
+                // Pixels on restart the fade
+                self.fade_levels[i] = self.fade;
+                self.pixel_color[*pixel as usize & 0b11]
+            } else {
+                // Pixels off blend toward the off color
+                self.fade_levels[i] = self.fade_levels[i].saturating_sub(1);
+                self.fade_color(self.fade_levels[i])
+            };
+
+            self.texture_buffer[i*4..i*4 + 4].copy_from_slice(&color);
         }
 
         // Present the texture buffer
         self.present_buffer();
     }
 
+    /// Fill the texture buffer with the off color and stop the fading pixels
+    fn clear(&mut self) {
+        let off_color = self.pixel_color[0];
+        for pixel in self.texture_buffer.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&off_color);
+        }
+        self.fade_levels.fill(0);
+
+        self.present_buffer();
+    }
 }
@@ -0,0 +1,235 @@
+use std::fmt::{self, Display};
+use std::io;
+
+/// Errors occurring during the execution of a Chip-8 program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChipError {
+    /// The instruction with the given raw bytes is not recognized
+    UnknownOpcode([u8; 2]),
+    /// A return instruction was executed with an empty stack
+    StackUnderflow,
+    /// A call instruction was executed with a full stack
+    StackOverflow {
+        /// Maximum stack depth of the configuration
+        depth: usize,
+    },
+    /// An instruction accessed memory past the end of memory in strict mode
+    MemoryOutOfBounds {
+        /// First address outside of memory accessed by the instruction
+        address: usize,
+    },
+    /// A subroutine run by step_over didn't return within the instruction limit
+    StepOverLimit {
+        /// Maximum number of instructions run
+        limit: u32,
+    },
+    /// A display buffer length doesn't match the current resolution
+    DisplaySizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// A rom doesn't fit in the memory after the rom start address
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    /// A font glyph is empty or wider than the font
+    InvalidFont {
+        /// Index of the invalid glyph
+        glyph: u8,
+    },
+    /// A rom doesn't contain any recognized opcode
+    NoRecognizedOpcode,
+    /// The first instruction of a rom jump outside of the rom
+    JumpOutOfRom {
+        /// Target address of the jump
+        address: u16,
+    },
+    /// A binary machine state is truncated or wasn't produced by ChipState::to_bytes
+    InvalidState,
+}
+
+// Implement Display for chip error
+impl Display for ChipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChipError::UnknownOpcode(raw) => {
+                write!(f, "unknown opcode 0x{:04X}", u16::from_be_bytes(*raw))
+            }
+            ChipError::StackUnderflow => write!(f, "return with an empty stack"),
+            ChipError::StackOverflow { depth } => {
+                write!(f, "call with a full stack of {} levels", depth)
+            }
+            ChipError::MemoryOutOfBounds { address } => {
+                write!(f, "memory access out of bounds at 0x{:X}", address)
+            }
+            ChipError::StepOverLimit { limit } => {
+                write!(f, "subroutine didn't return within {} instructions", limit)
+            }
+            ChipError::DisplaySizeMismatch { expected, found } => {
+                write!(f, "display buffer of {} pixels, expected {}", found, expected)
+            }
+            ChipError::RomTooLarge { size, max } => {
+                write!(f, "rom of {} bytes, the maximum is {}", size, max)
+            }
+            ChipError::InvalidFont { glyph } => write!(f, "invalid font glyph 0x{:X}", glyph),
+            ChipError::NoRecognizedOpcode => write!(f, "rom without any recognized opcode"),
+            ChipError::JumpOutOfRom { address } => {
+                write!(f, "rom jump outside of itself to 0x{:03X}", address)
+            }
+            ChipError::InvalidState => write!(f, "invalid binary machine state"),
+        }
+    }
+}
+
+impl std::error::Error for ChipError {}
+
+/// Errors occurring while loading a rom
+#[derive(Debug)]
+pub enum RomError {
+    /// The rom doesn't fit in the memory after the rom start address
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+    /// The rom file couldn't be read
+    Io(io::Error),
+}
+
+// Implement Display for rom error
+impl Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::TooLarge { size, max } => {
+                write!(f, "rom of {} bytes, the maximum is {}", size, max)
+            }
+            RomError::Io(error) => write!(f, "couldn't read the rom: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io(error) => Some(error),
+            RomError::TooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for RomError {
+    fn from(error: io::Error) -> Self {
+        RomError::Io(error)
+    }
+}
+
+/// Errors occurring while assembling a source, lines are numbered from 1
+#[derive(Debug)]
+pub enum AsmError {
+    /// The line doesn't match any supported instruction or directive
+    UnknownInstruction {
+        line: usize,
+    },
+    /// An operand is out of range or malformed
+    InvalidOperand {
+        line: usize,
+    },
+    /// A label is used but never defined
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    /// A label is defined twice
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    /// The assembled rom couldn't be loaded
+    Rom(RomError),
+}
+
+// Implement Display for assembler error
+impl Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnknownInstruction { line } => write!(f, "line {}: unknown instruction", line),
+            AsmError::InvalidOperand { line } => write!(f, "line {}: invalid operand", line),
+            AsmError::UndefinedLabel { line, label } => {
+                write!(f, "line {}: undefined label {}", line, label)
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label {} already defined", line, label)
+            }
+            AsmError::Rom(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AsmError::Rom(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<RomError> for AsmError {
+    fn from(error: RomError) -> Self {
+        AsmError::Rom(error)
+    }
+}
+
+/// Post-mortem report of the machine state when an error occurred
+#[derive(Clone, Debug)]
+pub struct ChipCrashReport {
+    /// The error returned by the emulator
+    pub error: ChipError,
+    /// Address of the instruction that caused the error
+    pub address: u16,
+    /// Raw bytes of the instruction that caused the error
+    pub instruction: [u8; 2],
+    /// Disassembled instruction with its operands,
+    /// DB followed by the raw word if not recognized
+    pub disassembly: String,
+
+    /// Machine state when the error occurred
+    pub registers: [u8; 16],
+    pub index_pointer: u16,
+    pub stack: Vec<u16>,
+
+    /// Formatted content of the memory around the instruction
+    pub memory_window: String,
+}
+
+// Implement Display for the crash report
+impl Display for ChipCrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Chip-8 crash report: {}", self.error)?;
+        write!(
+            f,
+            "Instruction: 0x{:03X}: {:02X} {:02X}",
+            self.address, self.instruction[0], self.instruction[1]
+        )?;
+        writeln!(f, "  ({})", self.disassembly)?;
+
+        // Registers
+        for (i, value) in self.registers.iter().enumerate() {
+            write!(f, "V{:X}: 0x{:02X}  ", i, value)?;
+            if i % 8 == 7 {
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "I: 0x{:03X}", self.index_pointer)?;
+
+        // Stack, from the most recent call
+        write!(f, "Stack:")?;
+        for address in self.stack.iter().rev() {
+            write!(f, " 0x{:03X}", address)?;
+        }
+        writeln!(f)?;
+
+        // Memory around the instruction
+        write!(f, "Memory:{}", self.memory_window)
+    }
+}
@@ -0,0 +1,60 @@
+use crate::CompatibilityProfile;
+
+/// Events emitted by the emulator to the hook set with set_event_hook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChipEvent {
+    /// The program counter moved outside of the loaded rom,
+    /// emitted once every time execution leave the rom region
+    LeftRom {
+        /// Address of the first instruction fetched outside of the rom
+        address: u16,
+    },
+
+    /// The sound timer reached zero during a 60 Hz timer tick,
+    /// emitted once every time the sound timer expire
+    SoundTimerExpired {
+        /// Frame count of the tick that expired the timer
+        frame: u64,
+    },
+
+    /// A draw instruction required more sprite rows than available before
+    /// the end of memory, only the available rows were drawn
+    SpriteClamped {
+        /// Address of the sprite
+        address: u16,
+        /// Rows required by the instruction
+        rows: u8,
+        /// Rows actually drawn
+        available: u8,
+    },
+
+    /// An unknown opcode was skipped, emitted with the Event unknown opcode policy
+    UnknownOpcode {
+        /// Address of the instruction
+        address: u16,
+        /// Opcode of the instruction
+        opcode: u16,
+    },
+
+    /// An opcode of an instruction set extension not enabled in the configuration
+    /// was executed, emitted only for the first one found
+    ExtensionOpcode {
+        /// Address of the instruction
+        address: u16,
+        /// Opcode of the instruction
+        opcode: u16,
+        /// Compatibility profile defining the opcode
+        profile: CompatibilityProfile,
+    },
+
+    /// The program executed the SUPER-CHIP 00FD exit instruction,
+    /// the machine is halted
+    Exit {
+        /// Address of the exit instruction
+        address: u16,
+    },
+
+    /// The emulator is shutting down, emitted by shutdown so frontends
+    /// can flush their own state before exiting
+    Shutdown,
+}
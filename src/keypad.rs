@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use sdl2::controller::{Button, GameController};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2::{GameControllerSubsystem, Sdl};
 
 /// Chip key enum
 #[derive(Clone, Copy)]
@@ -22,177 +27,300 @@ pub enum ChipKey {
     KeyF,
 }
 
+/// Every chip key ordered by value
+const CHIP_KEYS: [ChipKey; 16] = [
+    ChipKey::Key0, ChipKey::Key1, ChipKey::Key2, ChipKey::Key3,
+    ChipKey::Key4, ChipKey::Key5, ChipKey::Key6, ChipKey::Key7,
+    ChipKey::Key8, ChipKey::Key9, ChipKey::KeyA, ChipKey::KeyB,
+    ChipKey::KeyC, ChipKey::KeyD, ChipKey::KeyE, ChipKey::KeyF,
+];
+
+/// Error returned when converting a value outside of the keypad to a chip key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidKey(pub u8);
+
+// Implement Display for invalid key
+impl Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:02X} is not a chip-8 key", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+// Convert the key values 0x0 to 0xF to chip keys
+impl TryFrom<u8> for ChipKey {
+    type Error = InvalidKey;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        CHIP_KEYS.get(value as usize).copied().ok_or(InvalidKey(value))
+    }
+}
+
+// Convert chip keys to their value
+impl From<ChipKey> for u8 {
+    fn from(key: ChipKey) -> Self {
+        key as u8
+    }
+}
+
+/// Source of the chip-8 keys state
+pub trait ChipKeypad {
+    /// Return one of the keys currently pressed,
+    /// the one with the lowest value if more than one key is pressed
+    fn get_key(&self) -> Option<ChipKey>;
+
+    /// Return true if the given key is currently pressed
+    fn is_pressed(&self, key: ChipKey) -> bool;
+
+    /// Return the pressed state of every key as a bitmask,
+    /// bit 0 correspond to key 0x0 and bit 15 to key 0xF
+    fn get_key_bitmask(&self) -> u16 {
+        CHIP_KEYS
+            .iter()
+            .fold(0, |mask, key| mask | (self.is_pressed(*key) as u16) << *key as u16)
+    }
+}
+
 /*
 *
-*   Sdl event based keypad Implementation
+*   Null keypad Implementation
 *
 */
 
+/// Keypad without any key pressed, for headless runs
 #[derive(Default)]
+pub struct NullKeypad;
+
+// Implement Chip Keypad for null keypad
+impl ChipKeypad for NullKeypad {
+    fn get_key(&self) -> Option<ChipKey> {
+        None
+    }
+
+    fn is_pressed(&self, _key: ChipKey) -> bool {
+        false
+    }
+}
+
+/*
+*
+*   Sdl event based keypad Implementation
+*
+*/
+
+/// Default QWERTY bindings, the 4x4 block from 1 to V
+pub const DEFAULT_KEYMAP: [(Keycode, ChipKey); 16] = [
+    // Row 1
+    (Keycode::Num1, ChipKey::Key1),
+    (Keycode::Num2, ChipKey::Key2),
+    (Keycode::Num3, ChipKey::Key3),
+    (Keycode::Num4, ChipKey::KeyC),
+
+    // Row 2
+    (Keycode::Q, ChipKey::Key4),
+    (Keycode::W, ChipKey::Key5),
+    (Keycode::E, ChipKey::Key6),
+    (Keycode::R, ChipKey::KeyD),
+
+    // Row 3
+    (Keycode::A, ChipKey::Key7),
+    (Keycode::S, ChipKey::Key8),
+    (Keycode::D, ChipKey::Key9),
+    (Keycode::F, ChipKey::KeyE),
+
+    // Row 4
+    (Keycode::Z, ChipKey::KeyA),
+    (Keycode::X, ChipKey::Key0),
+    (Keycode::C, ChipKey::KeyB),
+    (Keycode::V, ChipKey::KeyF),
+];
+
 pub struct SdlKeypad {
-    key: Option<ChipKey> ,
+    /// Pressed state of every key, indexed by key value
+    keys: [bool; 16],
+
+    /// Chip key bound to every sdl keycode
+    keymap: HashMap<Keycode, ChipKey>,
+}
+
+// Use the default keymap
+impl Default for SdlKeypad {
+    fn default() -> Self {
+        Self::with_keymap(HashMap::from(DEFAULT_KEYMAP))
+    }
 }
 
 /// Implement sdl keypad methods
 impl SdlKeypad {
-    /// Return the current key pressed variable
-    pub fn get_key(&self) -> Option<ChipKey> {
-        self.key    
+    /// Create a keypad with the given bindings
+    pub fn with_keymap(keymap: HashMap<Keycode, ChipKey>) -> Self {
+        Self { keys: [false; 16], keymap }
+    }
+
+    /// Return the pressed state of every key, indexed by key value
+    pub fn get_keys(&self) -> [bool; 16] {
+        self.keys
     }
 
-    /// Process an sdl key event to update the key pressed variable
+    /// Process an sdl key event to update the pressed keys
     /// Return true if the event was processed
     pub fn process_sdl_event(&mut self, event: &Event) -> bool {
-        match event {
-            // Row 1
-            Event::KeyDown { keycode: Some(Keycode::Num1), .. } => {
-                self.key = Some(ChipKey::Key1);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Num1), .. } => {
-                self.key = None;
-                true
-            }
+        // Key repeat events set the key again and have no effect
+        let (keycode, pressed) = match event {
+            Event::KeyDown { keycode: Some(keycode), .. } => (keycode, true),
+            Event::KeyUp { keycode: Some(keycode), .. } => (keycode, false),
+            _ => return false,
+        };
 
-            Event::KeyDown { keycode: Some(Keycode::Num2), .. } => {
-                self.key = Some(ChipKey::Key2);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Num2), .. } => {
-                self.key = None;
+        match self.keymap.get(keycode) {
+            Some(key) => {
+                self.keys[*key as usize] = pressed;
                 true
             }
+            None => false,
+        }
+    }
+}
 
-            Event::KeyDown { keycode: Some(Keycode::Num3), .. } => {
-                self.key = Some(ChipKey::Key3);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Num3), .. } => {
-                self.key = None;
-                true
-            }
+// Implement Chip Keypad for sdl keypad
+impl ChipKeypad for SdlKeypad {
+    fn get_key(&self) -> Option<ChipKey> {
+        self.keys
+            .iter()
+            .position(|pressed| *pressed)
+            .map(|i| CHIP_KEYS[i])
+    }
 
-            Event::KeyDown { keycode: Some(Keycode::Num4), .. } => {
-                self.key = Some(ChipKey::KeyC);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Num4), .. } => {
-                self.key = None;
-                true
-            }
+    fn is_pressed(&self, key: ChipKey) -> bool {
+        self.keys[key as usize]
+    }
+}
 
-            // Row 2
-            Event::KeyDown { keycode: Some(Keycode::Q), .. } => {
-                self.key = Some(ChipKey::Key4);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Q), .. } => {
-                self.key = None;
-                true
-            }
+/*
+*
+*   Sdl game controller Implementation
+*
+*/
 
-            Event::KeyDown { keycode: Some(Keycode::W), .. } => {
-                self.key = Some(ChipKey::Key5);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::W), .. } => {
-                self.key = None;
-                true
-            }
+/// Default game controller bindings, the d-pad is mapped to the
+/// 2, 4, 6, 8 directional keys used by most games
+pub const DEFAULT_BUTTONMAP: [(Button, ChipKey); 10] = [
+    // D-pad
+    (Button::DPadUp, ChipKey::Key2),
+    (Button::DPadLeft, ChipKey::Key4),
+    (Button::DPadRight, ChipKey::Key6),
+    (Button::DPadDown, ChipKey::Key8),
 
-            Event::KeyDown { keycode: Some(Keycode::E), .. } => {
-                self.key = Some(ChipKey::Key6);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::E), .. } => {
-                self.key = None;
-                true
-            }
+    // Face buttons
+    (Button::A, ChipKey::Key5),
+    (Button::B, ChipKey::KeyA),
+    (Button::X, ChipKey::Key0),
+    (Button::Y, ChipKey::KeyB),
 
-            Event::KeyDown { keycode: Some(Keycode::R), .. } => {
-                self.key = Some(ChipKey::KeyD);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::R), .. } => {
-                self.key = None;
-                true
-            }
+    // Menu buttons
+    (Button::Start, ChipKey::KeyF),
+    (Button::Back, ChipKey::KeyE),
+];
 
-            // Row 3
-            Event::KeyDown { keycode: Some(Keycode::A), .. } => {
-                self.key = Some(ChipKey::Key7);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::A), .. } => {
-                self.key = None;
-                true
-            }
+pub struct SdlController {
+    subsystem: GameControllerSubsystem,
+    /// Controller in use, None until one is connected
+    controller: Option<GameController>,
 
-            Event::KeyDown { keycode: Some(Keycode::S), .. } => {
-                self.key = Some(ChipKey::Key8);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::S), .. } => {
-                self.key = None;
-                true
-            }
+    /// Pressed state of every key, indexed by key value
+    keys: [bool; 16],
 
-            Event::KeyDown { keycode: Some(Keycode::D), .. } => {
-                self.key = Some(ChipKey::Key9);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::D), .. } => {
-                self.key = None;
-                true
-            }
+    /// Chip key bound to every controller button
+    buttonmap: HashMap<Button, ChipKey>,
+}
 
-            Event::KeyDown { keycode: Some(Keycode::F), .. } => {
-                self.key = Some(ChipKey::KeyE);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::F), .. } => {
-                self.key = None;
-                true
-            }
+/// Implement sdl controller methods
+impl SdlController {
+    /// Open the first connected game controller with the default bindings,
+    /// a controller connected later is opened on its device added event
+    pub fn new(contex: &Sdl) -> Result<Self, String> {
+        Self::with_buttonmap(contex, HashMap::from(DEFAULT_BUTTONMAP))
+    }
 
-            // Row 4
-            Event::KeyDown { keycode: Some(Keycode::Z), .. } => {
-                self.key = Some(ChipKey::KeyA);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::Z), .. } => {
-                self.key = None;
-                true
-            }
+    /// Open the first connected game controller with the given bindings
+    pub fn with_buttonmap(contex: &Sdl, buttonmap: HashMap<Button, ChipKey>) -> Result<Self, String> {
+        let subsystem = contex.game_controller()?;
 
-            Event::KeyDown { keycode: Some(Keycode::X), .. } => {
-                self.key = Some(ChipKey::Key0);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::X), .. } => {
-                self.key = None;
-                true
-            }
+        let mut controller = Self {
+            subsystem,
+            controller: None,
 
-            Event::KeyDown { keycode: Some(Keycode::C), .. } => {
-                self.key = Some(ChipKey::KeyB);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::C), .. } => {
-                self.key = None;
-                true
-            }
+            keys: [false; 16],
 
-            Event::KeyDown { keycode: Some(Keycode::V), .. } => {
-                self.key = Some(ChipKey::KeyF);
-                true
-            }
-            Event::KeyUp { keycode: Some(Keycode::V), .. } => {
-                self.key = None;
+            buttonmap,
+        };
+        controller.open_first();
+
+        Ok(controller)
+    }
+
+    /// Return true if a game controller is in use
+    pub fn is_connected(&self) -> bool {
+        self.controller.is_some()
+    }
+
+    /// Process an sdl controller event to update the pressed keys
+    /// and track the controller connection
+    /// Return true if the event was processed
+    pub fn process_sdl_event(&mut self, event: &Event) -> bool {
+        let (button, pressed) = match event {
+            Event::ControllerButtonDown { button, .. } => (button, true),
+            Event::ControllerButtonUp { button, .. } => (button, false),
+
+            // Use the first controller connected
+            Event::ControllerDeviceAdded { .. } => {
+                if self.controller.is_none() {
+                    self.open_first();
+                }
+                return true;
+            }
+            // Release every key if the controller in use is removed
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if self.controller.as_ref().is_some_and(|c| c.instance_id() == *which) {
+                    self.controller = None;
+                    self.keys = [false; 16];
+                    self.open_first();
+                }
+                return true;
+            }
+            _ => return false,
+        };
+
+        match self.buttonmap.get(button) {
+            Some(key) => {
+                self.keys[*key as usize] = pressed;
                 true
             }
-            _ => { false }
+            None => false,
         }
     }
+
+    /// Open the first connected game controller, if any
+    fn open_first(&mut self) {
+        let count = self.subsystem.num_joysticks().unwrap_or(0);
+
+        self.controller = (0..count)
+            .filter(|i| self.subsystem.is_game_controller(*i))
+            .find_map(|i| self.subsystem.open(i).ok());
+    }
 }
 
+// Implement Chip Keypad for sdl controller
+impl ChipKeypad for SdlController {
+    fn get_key(&self) -> Option<ChipKey> {
+        self.keys
+            .iter()
+            .position(|pressed| *pressed)
+            .map(|i| CHIP_KEYS[i])
+    }
+
+    fn is_pressed(&self, key: ChipKey) -> bool {
+        self.keys[key as usize]
+    }
+}
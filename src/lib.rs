@@ -2,26 +2,111 @@ pub mod display;
 pub mod keypad;
 pub mod sound;
 pub mod fonts;
-
+pub mod opcodes;
+pub mod disasm;
+pub mod asm;
+pub mod event;
+pub mod error;
+pub mod peripheral;
+pub mod replay;
+pub mod rewind;
+mod state;
+mod video;
+
+#[cfg(feature = "serde")]
+mod serde_array;
+#[cfg(feature = "image")]
+mod screenshot;
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashSet, VecDeque};
 use std::fmt::Debug;
+use std::ops::Range;
 use std::fs::File;
-use std::io::{Read, Result};
-use std::path::Path;
-use std::time::{Duration, Instant};
-
-use fonts::DEFAULT_FONT;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use error::{ChipCrashReport, ChipError, RomError};
+use event::ChipEvent;
+use peripheral::Peripheral;
+use replay::{InputEvent, InputLog, Replay};
+use video::VideoBuffer;
+use fonts::{DEFAULT_BIG_FONT, DEFAULT_FONT};
 use keypad::ChipKey;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use display::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use display::{SCREEN_WIDTH, SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT};
 
 const FONT_ADDRESS: usize = 0x050;
+/// The big font is stored right after the 80 bytes small font
+const BIG_FONT_ADDRESS: usize = 0x0A0;
+const ROM_ADDRESS: usize = 0x200;
+
+/// Memory size of the original Chip-8 and of XO-CHIP, the largest
+/// memory addressable by the 16 bit index pointer
+const MIN_MEMORY_SIZE: usize = 0x1000;
+const MAX_MEMORY_SIZE: usize = 0x10000;
+
+/// Maximum number of instructions run by step_over before giving up
+const STEP_OVER_LIMIT: u32 = 1_000_000;
+
+/// Timer ticks in the measured instructions per second window, one emulated second
+const IPS_WINDOW_TICKS: usize = 60;
 
 /// Chip-8 emulator configuration struct
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChipEmulatorConfig {
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub font: [u8; 80],
+    /// SUPER-CHIP 8x10 font used by FX30, the memory is left empty if None
+    #[cfg_attr(feature = "serde", serde(with = "serde_array::option", default))]
+    pub big_font: Option<[u8; 160]>,
     pub instruction_per_second: u32,
 
+    /// Maximum number of instructions run by a single call to run_frame,
+    /// and by tick for every 60 Hz frame of the elapsed time, no limit if None
+    /// After a stall the excess instructions are dropped, the emulation
+    /// slow down slightly instead of freezing while trying to catch up
+    /// A limit below instruction_per_second / 60 slow down every frame
+    pub max_instructions_per_frame: Option<u32>,
+
+    /// Size of the program memory in bytes, 4096 for Chip-8 and 65536 for XO-CHIP
+    /// Clamped between the two
+    pub memory_size: usize,
+
+    /// Maximum number of nested subroutine calls, a deeper call return an error
+    pub max_stack_depth: usize,
+
+    /// Build a crash report of the machine state when step return an error
+    pub crash_report: bool,
+
+    /// Return an error on invalid memory accesses instead of recovering from them
+    pub strict: bool,
+
+    /// Handling of the instructions not matching any opcode of the instruction set
+    pub unknown_opcode_policy: UnknownOpcodePolicy,
+
+    /// Seed of the CXNN random number generator, the same seed with the same
+    /// inputs produce the same execution, seeded from entropy if None
+    pub rng_seed: Option<u64>,
+
+    /// Store the video buffer with one bit per pixel instead of one byte,
+    /// get_video_buffer still return one byte per pixel, unpacked on call,
+    /// use packed_video_buffer to read the packed pixels directly
+    /// The XO-CHIP planes are merged, a pixel is on if it's on any plane
+    pub packed_video: bool,
+
+    /// Instruction set extensions recognized by the interpreter
+    pub instruction_set: InstructionSet,
+
+    /// File storing the FX75 and FX85 flag registers, loaded on initialization
+    /// and saved by shutdown, the flags are not persisted if None
+    pub rpl_flags_file: Option<PathBuf>,
+
     /// Compatibility setting:
     /// During a shift operation copy the value form the registers Y
     /// into the registers X before shifting
@@ -31,6 +116,120 @@ pub struct ChipEmulatorConfig {
     /// In the BXNN instruction add the value of VX to XNN
     /// to obtain the offset value
     pub offset_jump_vx: bool,
+
+    /// Compatibility setting:
+    /// Effect of the 8XY1, 8XY2 and 8XY3 logical instructions on register VF
+    pub logic_vf: LogicVfBehavior,
+
+    /// Compatibility setting:
+    /// Mask the target of the jump and call instructions to an even address,
+    /// when disabled odd program counter addresses are allowed
+    pub align_pc: bool,
+
+    /// Compatibility setting:
+    /// Value of the index pointer after the FX55 and FX65 instructions,
+    /// the COSMAC VIP memory increment quirk is IncrementXPlusOne
+    pub store_load_index: StoreLoadIndex,
+
+    /// Compatibility setting:
+    /// Wrap the sprites crossing the screen edges to the opposite side,
+    /// when disabled they are clipped, the sprite starting position
+    /// always wrap
+    pub wrap_sprites: bool,
+
+    /// Compatibility setting:
+    /// Wait for the next 60 Hz tick after a draw instruction, as the COSMAC VIP
+    /// waiting for the vertical blank, run_frame and tick stop the frame
+    /// instructions after a draw
+    pub display_wait: bool,
+
+    /// Compatibility setting:
+    /// Clear the screen when switching between low and high resolution
+    pub clear_on_resolution_change: bool,
+
+    /// Compatibility setting:
+    /// FX0A wait for the pressed key to be released, as the COSMAC VIP,
+    /// when disabled the key is stored as soon as it's pressed
+    pub key_wait_release: bool,
+}
+
+/// Known Chip-8 platforms, each with its own instruction set and quirks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompatibilityProfile {
+    /// Original COSMAC VIP interpreter
+    Vip,
+    /// CHIP-48 interpreter for the HP-48 calculators
+    Chip48,
+    /// SUPER-CHIP 1.1 interpreter, extend CHIP-48 with high resolution,
+    /// scrolling and flag registers
+    Schip,
+    /// XO-CHIP extension, extend SUPER-CHIP with more memory,
+    /// drawing planes and audio patterns
+    XoChip,
+}
+
+// Implement compatibility profile methods
+impl CompatibilityProfile {
+    /// Return the instruction set supported by the profile
+    pub fn instruction_set(&self) -> InstructionSet {
+        match self {
+            CompatibilityProfile::Vip | CompatibilityProfile::Chip48 => InstructionSet::Chip8,
+            CompatibilityProfile::Schip => InstructionSet::SuperChip,
+            CompatibilityProfile::XoChip => InstructionSet::XoChip,
+        }
+    }
+}
+
+/// Instruction set recognized by the interpreter,
+/// each instruction set include the previous ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InstructionSet {
+    /// Original Chip-8 instructions
+    Chip8,
+    /// SUPER-CHIP extension instructions
+    SuperChip,
+    /// XO-CHIP extension instructions
+    XoChip,
+}
+
+/// Value of the index pointer after the FX55 and FX65 instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StoreLoadIndex {
+    /// Set the index pointer to I + X + 1, as done by the COSMAC VIP
+    IncrementXPlusOne,
+    /// Set the index pointer to I + X, as done by CHIP-48
+    IncrementX,
+    /// Leave the index pointer unchanged, as done by SUPER-CHIP
+    Unchanged,
+}
+
+/// Handling of the unknown opcodes by the interpreter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnknownOpcodePolicy {
+    /// Skip the instruction silently
+    Ignore,
+    /// Emit a ChipEvent::UnknownOpcode event and skip the instruction
+    Event,
+    /// Halt the machine on the instruction without returning an error
+    Halt,
+    /// Return a ChipError::UnknownOpcode error
+    Error,
+}
+
+/// Effect of the logical instructions (8XY1, 8XY2, 8XY3) on register VF
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogicVfBehavior {
+    /// Leave VF untouched, as done by CHIP-48, SUPER-CHIP and
+    /// most modern interpreters
+    Unchanged,
+    /// Reset VF to 0 after the operation, as done by the
+    /// original COSMAC VIP interpreter
+    ResetToZero,
 }
 
 // implement Default trait for config
@@ -38,21 +237,131 @@ impl Default for ChipEmulatorConfig {
     fn default() -> Self {
         Self {
             font: DEFAULT_FONT,
+            big_font: Some(DEFAULT_BIG_FONT),
             instruction_per_second: 700,
+            max_instructions_per_frame: None,
+            memory_size: MIN_MEMORY_SIZE,
+            max_stack_depth: 16,
+            crash_report: false,
+            strict: false,
+            unknown_opcode_policy: UnknownOpcodePolicy::Error,
+            rng_seed: None,
+            packed_video: false,
+            instruction_set: InstructionSet::Chip8,
+            rpl_flags_file: None,
 
             // Compatibility
             copy_y_on_shift: false,
             offset_jump_vx: false,
+            logic_vf: LogicVfBehavior::Unchanged,
+            align_pc: false,
+            store_load_index: StoreLoadIndex::Unchanged,
+            wrap_sprites: false,
+            display_wait: false,
+            clear_on_resolution_change: true,
+            key_wait_release: true,
+        }
+    }
+}
+
+// Implement TOML serialization, font validation and compatibility profile presets for config
+impl ChipEmulatorConfig {
+    /// Serialize the configuration to a TOML document
+    #[cfg(feature = "serde")]
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Deserialize a configuration written by to_toml
+    #[cfg(feature = "serde")]
+    pub fn from_toml(document: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(document)
+    }
+
+    /// Check that the configured fonts are usable
+    pub fn validate_fonts(&self) -> Result<(), ChipError> {
+        fonts::validate_font(&self.font)?;
+        if let Some(big_font) = &self.big_font {
+            fonts::validate_big_font(big_font)?;
+        }
+
+        Ok(())
+    }
+
+    /// Create a default configuration with the quirks
+    /// of the given compatibility profile
+    pub fn from_profile(profile: CompatibilityProfile) -> Self {
+        let store_load_index = match profile {
+            CompatibilityProfile::Vip => StoreLoadIndex::IncrementXPlusOne,
+            CompatibilityProfile::Chip48 => StoreLoadIndex::IncrementX,
+            CompatibilityProfile::Schip => StoreLoadIndex::Unchanged,
+            CompatibilityProfile::XoChip => StoreLoadIndex::IncrementXPlusOne,
+        };
+
+        // COSMAC VIP and XO-CHIP use V0 as the jump offset,
+        // CHIP-48 and SUPER-CHIP use VX
+        let offset_jump_vx = matches!(
+            profile,
+            CompatibilityProfile::Chip48 | CompatibilityProfile::Schip
+        );
+
+        // CHIP-48 and SUPER-CHIP shift VX in place
+        let copy_y_on_shift = !matches!(
+            profile,
+            CompatibilityProfile::Chip48 | CompatibilityProfile::Schip
+        );
+
+        // Only the COSMAC VIP logic routines clobber VF
+        let logic_vf = match profile {
+            CompatibilityProfile::Vip => LogicVfBehavior::ResetToZero,
+            _ => LogicVfBehavior::Unchanged,
+        };
+
+        Self {
+            instruction_set: profile.instruction_set(),
+            copy_y_on_shift,
+            offset_jump_vx,
+            logic_vf,
+            store_load_index,
+            display_wait: profile == CompatibilityProfile::Vip,
+            memory_size: if profile == CompatibilityProfile::XoChip {
+                MAX_MEMORY_SIZE
+            } else {
+                MIN_MEMORY_SIZE
+            },
+            ..Default::default()
         }
     }
+
+    /// Original COSMAC VIP configuration: shift VY into VX, jump offset V0,
+    /// logic instructions reset VF, FX55/FX65 increment I by X + 1
+    /// and draw instructions wait for the next 60 Hz tick
+    pub fn cosmac_vip() -> Self {
+        Self::from_profile(CompatibilityProfile::Vip)
+    }
+
+    /// CHIP-48 configuration: shift VX in place, jump offset VX,
+    /// VF unchanged by logic instructions and FX55/FX65 increment I by X
+    pub fn chip48() -> Self {
+        Self::from_profile(CompatibilityProfile::Chip48)
+    }
+
+    /// SUPER-CHIP 1.1 configuration: SUPER-CHIP instructions, shift VX in place,
+    /// jump offset VX, VF unchanged by logic instructions and I unchanged by FX55/FX65
+    pub fn superchip() -> Self {
+        Self::from_profile(CompatibilityProfile::Schip)
+    }
 }
 
 /// Chip-8 instruction struct
 #[derive(Clone, Copy)]
-struct ChipInstruction {
+pub struct ChipInstruction {
+    /// The two instruction bytes as stored in memory
     pub raw: [u8; 2],
 
+    /// The first nibble of the instruction
     pub op_code: u8,
+    /// The three remaining nibbles of the instruction
     pub parameter: [u8; 3],
 }
 
@@ -71,6 +380,36 @@ impl ChipInstruction {
             ],
         }
     }
+
+    /// Return the full 16 bit opcode
+    pub fn opcode(&self) -> u16 {
+        u16::from_be_bytes(self.raw)
+    }
+
+    /// Return the second nibble, usually the X register index
+    pub fn x(&self) -> u8 {
+        self.parameter[0]
+    }
+
+    /// Return the third nibble, usually the Y register index
+    pub fn y(&self) -> u8 {
+        self.parameter[1]
+    }
+
+    /// Return the last nibble
+    pub fn n(&self) -> u8 {
+        self.parameter[2]
+    }
+
+    /// Return the second byte
+    pub fn nn(&self) -> u8 {
+        self.raw[1]
+    }
+
+    /// Return the last 12 bits, usually an address
+    pub fn nnn(&self) -> u16 {
+        self.opcode() & 0x0FFF
+    }
 }
 
 // Implement Debug for chip instruction
@@ -86,13 +425,136 @@ impl Debug for ChipInstruction {
     }
 }
 
+/// Information detected by the static analysis of a rom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomInfo {
+    /// Size of the rom in bytes
+    pub len: usize,
+    /// True if SUPER-CHIP opcodes are present
+    pub superchip: bool,
+    /// True if XO-CHIP opcodes are present
+    pub xochip: bool,
+    /// Less extended profile supporting every detected opcode
+    pub suggested_profile: CompatibilityProfile,
+}
+
+/// Snapshot of the machine state, restored with ChipEmulator::restore
+/// Encoded in a binary format with to_bytes, or with serde
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChipState {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub program_counter: u16,
+    pub index_pointer: u16,
+    pub stack: Vec<u16>,
+
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+    /// SUPER-CHIP flag registers
+    pub rpl_flags: [u8; 16],
+
+    /// Pressed keys bitmask, bit 0 correspond to key 0x0 and bit 15 to key 0xF
+    pub keys: u16,
+    /// Key pressed during a FX0A wait, written to the register once released
+    pub wait_key: Option<u8>,
+
+    /// True if the display is in high resolution mode
+    pub hires: bool,
+    /// XO-CHIP drawing planes selected by FN01
+    pub planes: u8,
+    /// Pixels of the current resolution, one byte per pixel
+    pub video_buffer: Vec<u8>,
+}
+
+/// Outcome of a step_debug call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// The instruction at the program counter was executed
+    Executed,
+    /// The program counter reached the given breakpoint address,
+    /// the instruction wasn't executed
+    Breakpoint(u16),
+}
+
+/// Output of a frame run with ChipEmulator::run_frame
+#[derive(Clone, Copy, Debug)]
+pub struct FrameOutput<'a> {
+    /// Video buffer at the current resolution, as returned by get_video_buffer
+    pub video_buffer: &'a [u8],
+    /// True if the buffer was updated since the last frame
+    pub buffer_updated: bool,
+    /// True if the bell should be playing
+    pub bell: bool,
+}
+
+/// Pixels toggled by a draw instruction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawInfo {
+    /// Number of pixels turned on
+    pub pixels_set: u16,
+    /// Number of pixels turned off
+    pub pixels_cleared: u16,
+    /// True if at least one pixel was turned off
+    pub collision: bool,
+}
+
+impl DrawInfo {
+    /// Xor the sprite pixel on the screen pixel and record the change,
+    /// the sprite pixel hold the bit of the plane it's drawn on
+    fn toggle(&mut self, pixel: &mut u8, sprite_pixel: u8) {
+        if sprite_pixel == 0 {
+            return;
+        }
+
+        // If the sprite and screen pixel are both on
+        // turn off the screen pixel and record a collision
+        // If the sprite pixel is on and the screen pixel is off
+        // turn on the screen pixel
+        if *pixel & sprite_pixel != 0 {
+            self.pixels_cleared += 1;
+            self.collision = true;
+        } else {
+            self.pixels_set += 1;
+        }
+
+        *pixel ^= sprite_pixel;
+    }
+}
+
+/// Entry of the call stack pushed by a 2NNN instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackFrame {
+    /// Address of the 2NNN instruction that pushed the frame
+    pub call_address: u16,
+    /// Address execution return to with 00EE
+    pub return_address: u16,
+}
+
 /// Store all the components of a Chip-8 emulator
+///
+/// The machine never read the system clock, the timers are driven by
+/// run_frame, tick or tick_timers, so a rom run with a fixed rng_seed and the
+/// same inputs at the same cycles always reach the same state
 pub struct ChipEmulator {
-    /// 4KB program memory
-    memory: [u8; 4096],
+    /// Program memory, sized by the memory_size setting
+    memory: Vec<u8>,
     /// Video buffer to send to the screen implement on update
-    video_buffer: [u8; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+    /// Sized for the high resolution mode, only the first
+    /// width * height pixels of the current resolution are used
+    video_buffer: VideoBuffer,
     buffer_updated: bool,
+    /// One byte per pixel copy of a packed video buffer
+    /// returned by get_video_buffer, empty if not packed
+    unpacked_video: Vec<u8>,
+    /// True if the display is in high resolution mode
+    hires: bool,
+    /// XO-CHIP drawing planes bitmask, bit 0 for the first plane and bit 1
+    /// for the second one, every pixel store the bits of the planes it's on
+    planes: u8,
     /// Program registers
     registers: [u8; 16],
     /// The pointer to the current instruction
@@ -106,12 +568,85 @@ pub struct ChipEmulator {
     delay_timer: u8,
     /// Sound timer
     sound_timer: u8,
-
-    /// The key currently being pressed
-    key: Option<ChipKey>,
-
-    /// Clock used to keep the timer update at 60 Hz
-    last_timer_update: Instant,
+    /// Set when FX18 load the sound timer, cleared by take_sound_timer_reload
+    sound_timer_reloaded: bool,
+    /// Number of 60 Hz timer ticks since the emulator was created
+    frame_count: u64,
+
+    /// XO-CHIP 1-bit audio pattern, None until a pattern is loaded
+    audio_pattern: Option<[u8; 16]>,
+    /// XO-CHIP pitch register controlling the audio pattern playback rate
+    pitch: u8,
+    /// SUPER-CHIP flag registers, 8 on SUPER-CHIP and 16 on XO-CHIP
+    rpl_flags: [u8; 16],
+
+    /// Pressed state of the keys, bit i is set if the key i is pressed
+    keys: u16,
+    /// Keys pressed until the next key reading instruction
+    tapped_keys: u16,
+    /// Key pressed during a FX0A wait, written to the register once released
+    wait_key: Option<u8>,
+
+    /// Number of instructions executed since the initialization
+    cycles: u64,
+    /// Key state changes being recorded and replayed
+    recording: Option<InputLog>,
+    /// File the recording is written to by shutdown, if any
+    recording_file: Option<PathBuf>,
+    replay: Option<Replay>,
+
+    /// Instructions run since the last timer tick, instructions run
+    /// during each of the last 60 timer ticks and their sum
+    ips_tick_instructions: u64,
+    ips_window: VecDeque<u64>,
+    ips_window_instructions: u64,
+
+    /// Set to true when a draw instruction collide during the current frame
+    collision_this_frame: bool,
+    /// True if the last executed instruction was a draw
+    just_drew: bool,
+    /// Pixels toggled by the last draw instruction
+    last_draw_info: DrawInfo,
+
+    /// Size of the loaded rom in bytes
+    rom_size: usize,
+    /// True if the last instruction was fetched outside of the rom
+    outside_rom: bool,
+
+    /// Report of the last error, if crash reports are enabled
+    last_crash_report: Option<ChipCrashReport>,
+
+    /// Addresses where step_debug stop, and the breakpoint last reported
+    breakpoints: HashSet<u16>,
+    breakpoint_hit: Option<u16>,
+
+    /// Memory mapped peripherals, empty by default
+    peripherals: Vec<Peripheral>,
+
+    /// Random number generator of the CXNN instruction
+    rng: StdRng,
+
+    /// Fractions of instruction and timer tick carried between tick calls
+    tick_instruction_carry: f64,
+    tick_timer_carry: f64,
+
+    /// Emulated time elapsed per real time unit, and the fractions of
+    /// instruction and timer tick carried between run_frame calls
+    speed_multiplier: f64,
+    frame_instruction_carry: f64,
+    frame_timer_carry: f64,
+
+    /// While paused no instruction is run and the timers are frozen
+    paused: bool,
+    /// Set when an instruction returned an error
+    halted: bool,
+    /// Error that halted the emulator, returned again by the run methods
+    halt_error: Option<ChipError>,
+
+    /// Set once an extension opcode event was emitted
+    extension_warned: bool,
+    /// Optional hook called with every emitted event
+    event_hook: Option<Box<dyn FnMut(ChipEvent)>>,
 
     /// Store the configuration struct
     config: ChipEmulatorConfig,
@@ -120,44 +655,214 @@ pub struct ChipEmulator {
 // implement constructor and methods for the Chip-8 emulator
 impl ChipEmulator {
     /// Instantiate and initialize a new Chip-8 emulator
+    /// Return a ChipError::InvalidFont error if a configured font is malformed
     pub fn initialize(
         config: ChipEmulatorConfig, 
-    ) -> Self {
+    ) -> Result<Self, ChipError> {
+        config.validate_fonts()?;
+
         let mut emulator = Self {
             // Initialize memory to zeros
-            memory: [0u8; 4096],
+            memory: vec![0u8; config.memory_size.clamp(MIN_MEMORY_SIZE, MAX_MEMORY_SIZE)],
             // Initialize video buffer
-            video_buffer: [0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+            video_buffer: VideoBuffer::new(config.packed_video),
             buffer_updated: true,
+            unpacked_video: Vec::new(),
+            hires: false,
+            // Draw on the first plane only
+            planes: 1,
             // Set the program counter to 0x200
             program_counter: 0x200u16,
             // Set index pointer to zero
             index_pointer: 0u16,
             // Create the stack
-            stack: Vec::with_capacity(32),
+            stack: Vec::with_capacity(config.max_stack_depth),
             // Initialize registers to 0
             registers: [0u8; 16],
 
             // Initialize timer to 0
             delay_timer: 0u8,
             sound_timer: 0u8,
+            sound_timer_reloaded: false,
+            frame_count: 0u64,
+
+            // No audio pattern and default pitch of 4000 Hz
+            audio_pattern: None,
+            pitch: 64u8,
+            rpl_flags: [0u8; 16],
+
+            // Initialize input keys to released
+            keys: 0u16,
+            tapped_keys: 0u16,
+            wait_key: None,
+
+            cycles: 0,
+            recording: None,
+            recording_file: None,
+            replay: None,
 
-            // Initialize input key to None
-            key: None,
+            // Start the first measurement window
+            ips_tick_instructions: 0,
+            ips_window: VecDeque::with_capacity(IPS_WINDOW_TICKS),
+            ips_window_instructions: 0,
 
-            // Set last timer update to now
-            last_timer_update: Instant::now(),
+            // No collision occurred yet
+            collision_this_frame: false,
+            just_drew: false,
+            last_draw_info: DrawInfo::default(),
+
+            // No rom loaded yet
+            rom_size: 0,
+            outside_rom: false,
+
+            // No error occurred yet
+            last_crash_report: None,
+
+            breakpoints: HashSet::new(),
+            breakpoint_hit: None,
+
+            // Plain memory without peripherals
+            peripherals: Vec::new(),
+
+            rng: match config.rng_seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+
+            tick_instruction_carry: 0.,
+            tick_timer_carry: 0.,
+
+            // Real time speed
+            speed_multiplier: 1.,
+            frame_instruction_carry: 0.,
+            frame_timer_carry: 0.,
+
+            // Running by default
+            paused: false,
+            halted: false,
+            halt_error: None,
+
+            extension_warned: false,
+            // No event hook by default
+            event_hook: None,
 
             // Save the config
             config,
         };
 
         // Store the font in the program memory during initialization
-        emulator.memory[FONT_ADDRESS..FONT_ADDRESS + emulator.config.font.len()]
-            .copy_from_slice(&emulator.config.font);
+        emulator.load_fonts();
+
+        // Load the persisted flag registers, a missing file leave them cleared
+        if let Some(path) = &emulator.config.rpl_flags_file {
+            if let Ok(flags) = std::fs::read(path) {
+                let len = flags.len().min(emulator.rpl_flags.len());
+                emulator.rpl_flags[..len].copy_from_slice(&flags[..len]);
+            }
+        }
 
         // Return the initialized emulator
-        emulator
+        Ok(emulator)
+    }
+
+    /// Store the configured fonts in the program memory
+    fn load_fonts(&mut self) {
+        self.memory[FONT_ADDRESS..FONT_ADDRESS + self.config.font.len()]
+            .copy_from_slice(&self.config.font);
+        if let Some(big_font) = self.config.big_font {
+            self.memory[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + big_font.len()]
+                .copy_from_slice(&big_font);
+        }
+    }
+
+    /// Return the emulator to its power-on state, as if it was just initialized
+    /// with its configuration, the whole memory is zeroed and the rom unloaded
+    /// The paused state, breakpoints, recording and replay are cleared,
+    /// only the event hook is kept
+    pub fn reset(&mut self) {
+        let event_hook = self.event_hook.take();
+
+        *self = Self::initialize(self.config.clone())
+            .expect("the configuration was validated when the emulator was initialized");
+        self.event_hook = event_hook;
+    }
+
+    /// Reinitialize the machine state without reallocating it, the loaded
+    /// rom bytes and the configuration are kept
+    /// The memory outside of the rom is zeroed and the fonts reloaded,
+    /// a rom that modified its own bytes is not restored
+    /// A recording in progress restart from the reset machine, the previous
+    /// events are discarded, and a seeded random generator is reseeded
+    pub fn reset_keep_rom(&mut self) {
+        // Zero the memory around the rom and reload the fonts
+        let rom_end = ROM_ADDRESS + self.rom_size;
+        self.memory[..ROM_ADDRESS].fill(0);
+        self.memory[rom_end..].fill(0);
+        self.load_fonts();
+
+        // Clear the screen in low resolution mode
+        self.hires = false;
+        let (width, height) = self.get_screen_size();
+        self.video_buffer.clear((width * height) as usize);
+        self.buffer_updated = true;
+
+        self.registers = [0u8; 16];
+        self.program_counter = ROM_ADDRESS as u16;
+        self.index_pointer = 0;
+        self.stack.clear();
+
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.sound_timer_reloaded = false;
+        self.frame_count = 0;
+
+        self.audio_pattern = None;
+        self.pitch = 64;
+        self.planes = 1;
+
+        self.tapped_keys = 0;
+        self.wait_key = None;
+
+        // The replayed and recorded events refer to the cycles of the previous run
+        self.cycles = 0;
+        self.replay = None;
+        if self.recording.is_some() {
+            self.start_recording();
+        }
+
+        if let Some(seed) = self.config.rng_seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+
+        self.collision_this_frame = false;
+        self.just_drew = false;
+        self.last_draw_info = DrawInfo::default();
+        self.outside_rom = false;
+
+        self.last_crash_report = None;
+        self.breakpoint_hit = None;
+
+        self.tick_instruction_carry = 0.;
+        self.tick_timer_carry = 0.;
+        self.frame_instruction_carry = 0.;
+        self.frame_timer_carry = 0.;
+
+        self.halted = false;
+        self.halt_error = None;
+        self.extension_warned = false;
+    }
+
+    /// Reset the emulator to its power-on state and load the given rom bytes
+    /// An oversized rom is rejected before the reset
+    pub fn reset_and_load(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        let max = self.memory.len() - ROM_ADDRESS;
+        if rom.len() > max {
+            return Err(RomError::TooLarge { size: rom.len(), max });
+        }
+
+        self.reset();
+
+        self.load_rom_bytes(rom)
     }
 
     /// Get the current bell status based on the sound timer
@@ -166,111 +871,953 @@ impl ChipEmulator {
         self.sound_timer != 0
     }
 
-    /// Update the key pressed value
+    /// Return the current value of the sound timer
+    pub fn get_sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// Return the current value of the delay timer
+    pub fn get_delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    /// Return true if the program loaded the sound timer with FX18 since
+    /// the last call, even with a value lower or equal to the current one
+    pub fn take_sound_timer_reload(&mut self) -> bool {
+        std::mem::take(&mut self.sound_timer_reloaded)
+    }
+
+    /// Return the number of frames since the emulator was created
+    /// The counter is incremented with every 60 Hz timer tick,
+    /// not with every rendered frame
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Return the XO-CHIP audio pattern if the program loaded one
+    pub fn get_audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// Return the playback rate of the audio pattern in bits per second
+    /// computed from the pitch register
+    pub fn get_audio_sample_rate(&self) -> f32 {
+        4000. * 2f32.powf((self.pitch as f32 - 64.) / 48.)
+    }
+
+    /// Update the key pressed value, all other keys are released
     pub fn update_key(&mut self, key: Option<ChipKey>) {
-        self.key = key;
+        self.set_keys(key.map_or(0, |key| 1 << key as u16));
+    }
+
+    /// Press the given key until it's released with release_key
+    pub fn press_key(&mut self, key: ChipKey) {
+        self.set_keys(self.keys | 1 << key as u16);
+    }
+
+    /// Release the given key
+    /// A key pressed with tap_key stay pressed until consumed
+    pub fn release_key(&mut self, key: ChipKey) {
+        self.set_keys(self.keys & !(1 << key as u16));
+    }
+
+    /// Press the given key for the next key reading instruction only
+    /// (EX9E, EXA1 or FX0A), the key is released once the instruction is executed
+    /// The tap is independent from the state set with press_key and release_key,
+    /// the key is seen as pressed if either of them is pressed
+    pub fn tap_key(&mut self, key: ChipKey) {
+        self.tapped_keys |= 1 << key as u16;
+    }
+
+    /// Set the state of all the keys from a bitmask,
+    /// bit 0 correspond to key 0x0 and bit 15 to key 0xF
+    pub fn set_key_bitmask(&mut self, mask: u16) {
+        self.set_keys(mask);
+    }
+
+    /// Return the state of all the keys as a bitmask,
+    /// bit 0 correspond to key 0x0 and bit 15 to key 0xF
+    pub fn key_bitmask(&self) -> u16 {
+        self.keys
+    }
+
+    /// Return true if the given key is either held or tapped,
+    /// every key is tracked independently
+    pub fn is_key_pressed(&self, key: ChipKey) -> bool {
+        self.pressed_keys() & (1 << u8::from(key)) != 0
+    }
+
+    /// Start recording the key state changes, replacing any previous recording
+    /// The current key state is recorded first
+    /// The taps of tap_key are not recorded
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputLog {
+            rng_seed: self.config.rng_seed,
+            events: vec![InputEvent { cycle: self.cycles, keys: self.keys }],
+        });
+    }
+
+    /// Start recording the key state changes as start_recording,
+    /// the recording is written to the given file by shutdown
+    pub fn start_recording_to(&mut self, path: &Path) {
+        self.start_recording();
+        self.recording_file = Some(path.to_path_buf());
+    }
+
+    /// Stop recording and return the recorded key state changes,
+    /// empty if no recording was started
+    /// The recording is no longer written by shutdown
+    pub fn take_recording(&mut self) -> InputLog {
+        self.recording_file = None;
+        self.recording.take().unwrap_or_default()
+    }
+
+    /// Replay the given key state changes, every event is applied before
+    /// the instruction of its cycle is executed
+    /// The random generator is reseeded with the seed of the log, if any,
+    /// replaying a log from the start of the same rom reproduce the
+    /// recorded execution
+    pub fn load_replay(&mut self, log: InputLog) {
+        if let Some(seed) = log.rng_seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+
+        self.replay = Some(Replay::new(log));
+    }
+
+    /// Return true until every event of the loaded replay was applied
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Return a slice containing the video buffer, one byte per pixel,
+    /// and a boolean variable set to true if the buffer was updated since
+    /// the last call to this function
+    pub fn get_video_buffer(&mut self) -> (&[u8], bool) {
+        let (width, height) = self.get_screen_size();
+        let len = (width * height) as usize;
+
+        let buffer_updated = self.buffer_updated;
+        self.buffer_updated = false;
+
+        // A packed buffer is unpacked for the displays reading bytes
+        if self.config.packed_video {
+            self.unpacked_video.clear();
+            self.unpacked_video.extend(self.video_buffer.pixels(len));
+
+            (&self.unpacked_video, buffer_updated)
+        } else {
+            (self.video_buffer.raw(len), buffer_updated)
+        }
+    }
+
+    /// Return the packed video buffer at the current resolution, every byte
+    /// contain eight pixels with the leftmost pixel in the most significant bit
+    /// Return None if packed_video is disabled
+    pub fn packed_video_buffer(&self) -> Option<&[u8]> {
+        let (width, height) = self.get_screen_size();
+        self.config
+            .packed_video
+            .then(|| self.video_buffer.raw((width * height) as usize))
+    }
+
+    /// Return the number of bytes used to store the video buffer
+    pub fn video_buffer_footprint(&self) -> usize {
+        self.video_buffer.footprint()
+    }
+
+    /// Return the video buffer at the current resolution with one byte
+    /// per pixel, whatever the packed_video setting
+    pub fn unpacked_frame(&self) -> Vec<u8> {
+        let (width, height) = self.get_screen_size();
+        self.video_buffer.pixels((width * height) as usize).collect()
+    }
+
+    /// Capture the full machine state
+    pub fn snapshot(&self) -> ChipState {
+        ChipState {
+            memory: self.memory.clone(),
+            registers: self.registers,
+            program_counter: self.program_counter,
+            index_pointer: self.index_pointer,
+            stack: self.stack.clone(),
+
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rpl_flags: self.rpl_flags,
+
+            keys: self.keys,
+            wait_key: self.wait_key,
+
+            planes: self.planes,
+
+            hires: self.hires,
+            video_buffer: self.unpacked_frame(),
+        }
+    }
+
+    /// Restore a machine state captured with snapshot
+    /// Return a ChipError::InvalidState error and leave the emulator untouched
+    /// if the state memory size differ from the configured one or its stack
+    /// is deeper than max_stack_depth
+    pub fn restore(&mut self, state: &ChipState) -> Result<(), ChipError> {
+        let stack_too_deep = state.stack.len() > self.config.max_stack_depth;
+        if state.memory.len() != self.memory.len() || stack_too_deep {
+            return Err(ChipError::InvalidState);
+        }
+
+        self.memory.copy_from_slice(&state.memory);
+        self.registers = state.registers;
+        self.program_counter = state.program_counter;
+        self.index_pointer = state.index_pointer;
+        self.stack = state.stack.clone();
+
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        // The sound backend restart the tone for the restored timer
+        self.sound_timer_reloaded = true;
+
+        self.audio_pattern = state.audio_pattern;
+        self.pitch = state.pitch;
+        self.rpl_flags = state.rpl_flags;
+
+        self.keys = state.keys;
+        self.tapped_keys = 0;
+        self.wait_key = state.wait_key;
+
+        self.planes = state.planes;
+
+        // Restore the resolution without the resolution change side effects
+        self.hires = state.hires;
+        let (width, height) = self.get_screen_size();
+        let len = (width * height) as usize;
+
+        self.video_buffer.clear(len);
+        for (i, pixel) in state.video_buffer.iter().take(len).enumerate() {
+            self.video_buffer.set(i, *pixel);
+        }
+        self.buffer_updated = true;
+
+        Ok(())
+    }
+
+    /// Return a copy of the video buffer at the current resolution,
+    /// one byte per pixel
+    pub fn save_display(&self) -> Vec<u8> {
+        self.unpacked_frame()
+    }
+
+    /// Restore a video buffer saved with save_display,
+    /// the buffer length must match the current resolution
+    pub fn restore_display(&mut self, buffer: &[u8]) -> Result<(), ChipError> {
+        let (width, height) = self.get_screen_size();
+        let len = (width * height) as usize;
+
+        if buffer.len() != len {
+            return Err(ChipError::DisplaySizeMismatch { expected: len, found: buffer.len() });
+        }
+
+        for (i, pixel) in buffer.iter().enumerate() {
+            self.video_buffer.set(i, *pixel);
+        }
+        self.buffer_updated = true;
+
+        Ok(())
+    }
+
+    /// Return the width and height of the current resolution
+    pub fn get_screen_size(&self) -> (u32, u32) {
+        if self.hires {
+            (HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
+    }
+
+    /// Switch between low and high resolution mode, as done by the 00FE and 00FF
+    /// instructions, the screen is cleared if clear_on_resolution_change is enabled
+    pub fn set_hires(&mut self, hires: bool) {
+        self.set_resolution(hires);
+    }
+
+    /// Load a chip-8 rom from a file
+    pub fn load_rom(&mut self, file_path: &str) -> Result<(), RomError> {
+        // Open the rom file
+        let path = Path::new(file_path);
+        let mut f = File::open(path)?;
+
+        // Reject oversized files before reading them
+        let size = f.metadata()?.len() as usize;
+        let max = self.memory.len() - ROM_ADDRESS;
+        if size > max {
+            return Err(RomError::TooLarge { size, max });
+        }
+
+        // Read the whole file and load it in memory
+        let mut rom = Vec::with_capacity(size);
+        f.read_to_end(&mut rom)?;
+
+        self.load_rom_bytes(&rom)
+    }
+
+    /// Replace the running rom with the one in the given file, the machine
+    /// is reset once the file was read, a failed read leave it untouched
+    pub fn swap_rom(&mut self, file_path: &str) -> Result<(), RomError> {
+        let rom = std::fs::read(file_path)?;
+
+        self.reset_and_load(&rom)
+    }
+
+    /// Load a chip-8 rom from any reader, such as a network stream
+    /// The reader is read until its end, at most one byte past the largest rom
+    pub fn load_rom_reader(&mut self, reader: impl Read) -> Result<(), RomError> {
+        let max = self.memory.len() - ROM_ADDRESS;
+
+        // Read one more byte than the maximum to detect oversized roms
+        let mut rom = Vec::new();
+        reader.take(max as u64 + 1).read_to_end(&mut rom)?;
+
+        self.load_rom_bytes(&rom)
+    }
+
+    /// Load a chip-8 rom from a byte slice, such as a rom embedded with include_bytes
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        const START_ADDRESS: usize = ROM_ADDRESS;
+
+        // The rom must fit in memory after the start address
+        let max = self.memory.len() - START_ADDRESS;
+        if rom.len() > max {
+            return Err(RomError::TooLarge { size: rom.len(), max });
+        }
+
+        // Copy the rom in memory
+        self.memory[START_ADDRESS..START_ADDRESS + rom.len()].copy_from_slice(rom);
+
+        // Set the program counter to the rom start address
+        self.program_counter = START_ADDRESS as u16;
+        self.rom_size = rom.len();
+        self.outside_rom = false;
+
+        Ok(())
+    }
+
+    /// Check a rom without running it, every aligned word is decoded and
+    /// the detected extension opcodes are used to suggest a profile
+    /// Data stored in the rom may be decoded as opcodes,
+    /// the result is only a hint
+    pub fn validate_rom(bytes: &[u8]) -> Result<RomInfo, ChipError> {
+        // The rom must fit in the largest memory after the start address
+        let max = MAX_MEMORY_SIZE - ROM_ADDRESS;
+        if bytes.len() > max {
+            return Err(ChipError::RomTooLarge { size: bytes.len(), max });
+        }
+
+        let mut recognized = false;
+        let mut superchip = false;
+        // Only XO-CHIP has more than 4KB of memory
+        let mut xochip = bytes.len() > MIN_MEMORY_SIZE - ROM_ADDRESS;
+
+        for word in bytes.chunks_exact(2) {
+            let Some(info) = opcodes::lookup(u16::from_be_bytes([word[0], word[1]])) else {
+                continue;
+            };
+
+            recognized = true;
+            match info.profile.instruction_set() {
+                InstructionSet::Chip8 => {}
+                InstructionSet::SuperChip => superchip = true,
+                InstructionSet::XoChip => xochip = true,
+            }
+        }
+
+        if !recognized {
+            return Err(ChipError::NoRecognizedOpcode);
+        }
+
+        // A jump as first instruction must land inside the rom
+        if let [high, low, ..] = *bytes {
+            let instruction = ChipInstruction::new([high, low]);
+            let rom_end = ROM_ADDRESS + bytes.len();
+
+            let target = instruction.nnn();
+            if instruction.op_code == 0x1 && !(ROM_ADDRESS..rom_end).contains(&(target as usize)) {
+                return Err(ChipError::JumpOutOfRom { address: target });
+            }
+        }
+
+        let suggested_profile = if xochip {
+            CompatibilityProfile::XoChip
+        } else if superchip {
+            CompatibilityProfile::Schip
+        } else {
+            CompatibilityProfile::Vip
+        };
+
+        Ok(RomInfo { len: bytes.len(), superchip, xochip, suggested_profile })
+    }
+
+    /// Return the memory region occupied by the loaded rom, the end of
+    /// a rom filling a 64 KiB memory is past the 16 bit address space
+    pub fn rom_range(&self) -> Range<usize> {
+        ROM_ADDRESS..ROM_ADDRESS + self.rom_size
+    }
+
+    /// Set a hook called with every event emitted by the emulator
+    pub fn set_event_hook(&mut self, hook: Box<dyn FnMut(ChipEvent)>) {
+        self.event_hook = Some(hook);
+    }
+
+    /// Map a peripheral to its address range, the reads and writes of the
+    /// instructions and of read_memory and write_memory in the range are
+    /// routed to the peripheral instead of the program memory
+    /// The first added peripheral take precedence if ranges overlap
+    pub fn add_peripheral(&mut self, peripheral: Peripheral) {
+        self.peripherals.push(peripheral);
+    }
+
+    /// Remove all the peripherals, restoring plain memory accesses
+    pub fn clear_peripherals(&mut self) {
+        self.peripherals.clear();
+    }
+
+    /// Read the byte at the given address, from a peripheral if one is mapped there
+    pub fn read_memory(&mut self, address: u16) -> u8 {
+        self.read_byte(address as usize)
+    }
+
+    /// Write the given bytes starting at the given address,
+    /// to a peripheral if one is mapped there
+    pub fn write_memory(&mut self, address: u16, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write_byte(address as usize + i, *byte);
+        }
+    }
+
+    /// Tear down the emulator, to be called by frontends before exiting
+    ///
+    /// The flush operations are run in the following order:
+    /// 1. the event hook receive a ChipEvent::Shutdown event
+    /// 2. the input recording is written to the file of start_recording_to, if any
+    /// 3. the flag registers are saved to the rpl_flags_file, if any
+    ///
+    /// Every operation is run even if a previous one failed,
+    /// the first error is returned
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.emit_event(ChipEvent::Shutdown);
+
+        let recording = match self.recording_file.clone() {
+            Some(path) => self.take_recording().save(&path),
+            None => Ok(()),
+        };
+
+        let flags = match &self.config.rpl_flags_file {
+            Some(path) => std::fs::write(path, self.rpl_flags),
+            None => Ok(()),
+        };
+
+        recording.and(flags)
+    }
+
+    /// Return the SUPER-CHIP flag registers written by FX75
+    pub fn rpl_flags(&self) -> &[u8; 16] {
+        &self.rpl_flags
+    }
+
+    /// Remove the event hook
+    pub fn clear_event_hook(&mut self) {
+        self.event_hook = None;
+    }
+
+    /// Wait for the right amount of time to start the next clock cycle
+    pub fn get_cycle_duration(&self) -> Duration {
+        Duration::from_secs_f64(1. / self.config.instruction_per_second as f64)
     }
 
-    /// Return a slice containing the video buffer and a boolean
-    /// variable set to true if the buffer was updated since
-    /// the last call to this function
-    pub fn get_video_buffer(&mut self) -> (&[u8], bool) {
-        let output = (
-            &self.video_buffer[0..self.video_buffer.len()],
-            self.buffer_updated,
-        );
-        self.buffer_updated = false;
+    /// Run the given number of instructions as a single frame,
+    /// capped to the max_instructions_per_frame setting if any,
+    /// then tick the timers once
+    /// Both are scaled by the speed multiplier, the fractions carried
+    /// to the next frames
+    /// Return the frame output for the host to render and play,
+    /// stop at the first error
+    pub fn run_frame(&mut self, instructions: u32) -> Result<FrameOutput<'_>, ChipError> {
+        // A paused or halted frame only return the current output,
+        // or the error that halted the emulator
+        if self.paused || self.halted {
+            if let Some(error) = self.halt_error {
+                return Err(error);
+            }
+
+            let bell = self.get_bell_status();
+            let (video_buffer, buffer_updated) = self.get_video_buffer();
+
+            return Ok(FrameOutput { video_buffer, buffer_updated, bell });
+        }
+
+        // Reset the frame collision flag
+        self.collision_this_frame = false;
+
+        // Scale the frame to the speed multiplier
+        self.frame_instruction_carry += instructions as f64 * self.speed_multiplier;
+        let mut instructions = self.frame_instruction_carry.floor();
+        self.frame_instruction_carry -= instructions;
+
+        if let Some(max_instructions) = self.config.max_instructions_per_frame {
+            instructions = instructions.min(max_instructions as f64 * self.speed_multiplier.max(1.));
+        }
+
+        for _ in 0..instructions as u32 {
+            self.step()?;
+
+            // With display_wait a draw end the frame
+            if self.config.display_wait && self.just_drew {
+                break;
+            }
+        }
+
+        // Decrement the timers once per emulated frame
+        self.frame_timer_carry += self.speed_multiplier;
+        while self.frame_timer_carry >= 1. {
+            self.tick_timers();
+            self.frame_timer_carry -= 1.;
+        }
+
+        let bell = self.get_bell_status();
+        let (video_buffer, buffer_updated) = self.get_video_buffer();
+
+        Ok(FrameOutput { video_buffer, buffer_updated, bell })
+    }
+
+    /// Set the speed of the emulation relative to real time, 2 run two emulated
+    /// frames per run_frame call and 0.5 half a frame, the timers included
+    /// Also scale the elapsed time of tick, negative values are clamped to 0
+    pub fn set_speed_multiplier(&mut self, multiplier: f64) {
+        self.speed_multiplier = multiplier.max(0.);
+    }
+
+    /// Return the speed of the emulation relative to real time
+    pub fn speed_multiplier(&self) -> f64 {
+        self.speed_multiplier
+    }
+
+    /// Return the instructions actually run per emulated second, measured
+    /// over a sliding window of the last 60 timer ticks
+    /// Lower than instruction_per_second when the frame limit or display_wait
+    /// dropped instructions, extrapolated from the elapsed ticks during the
+    /// first emulated second and 0 before the first timer tick
+    pub fn measured_ips(&self) -> f64 {
+        if self.ips_window.is_empty() {
+            return 0.;
+        }
+
+        self.ips_window_instructions as f64 * IPS_WINDOW_TICKS as f64 / self.ips_window.len() as f64
+    }
+
+    /// Move the instructions run since the last timer tick in the
+    /// measurement window, dropping the oldest tick once the window is full
+    fn measure_ips(&mut self) {
+        if self.ips_window.len() == IPS_WINDOW_TICKS {
+            if let Some(oldest) = self.ips_window.pop_front() {
+                self.ips_window_instructions -= oldest;
+            }
+        }
+
+        let instructions = std::mem::take(&mut self.ips_tick_instructions);
+        self.ips_window.push_back(instructions);
+        self.ips_window_instructions += instructions;
+    }
+
+    /// Return true if any draw instruction caused a collision
+    /// during the last frame run with run_frame
+    pub fn collision_occurred_last_frame(&self) -> bool {
+        self.collision_this_frame
+    }
+
+    /// Return true if the last executed instruction was a draw,
+    /// used with display_wait to end the frame instruction batch
+    pub fn just_drew(&self) -> bool {
+        self.just_drew
+    }
+
+    /// Return the pixels toggled by the last draw instruction
+    pub fn last_draw_info(&self) -> DrawInfo {
+        self.last_draw_info
+    }
+
+    /// Advance the emulation by the given number of milliseconds, running
+    /// the instructions and 60 Hz timer ticks due in that time
+    /// The fractions left over are carried to the next call, the instructions
+    /// over max_instructions_per_frame for every elapsed 60 Hz frame are dropped,
+    /// callers drop a stall by clamping the elapsed time
+    /// Never sleep nor read the system clock, suited to frontends driven
+    /// by an external clock such as requestAnimationFrame
+    pub fn tick(&mut self, elapsed_ms: f64) -> Result<(), ChipError> {
+        if self.paused || self.halted {
+            return self.halt_result();
+        }
+
+        // Reset the frame collision flag
+        self.collision_this_frame = false;
+
+        let elapsed_ms = elapsed_ms.max(0.) * self.speed_multiplier;
+        let instructions_per_ms = self.config.instruction_per_second as f64 / 1000.;
+        self.tick_instruction_carry += elapsed_ms * instructions_per_ms;
+        self.tick_timer_carry += elapsed_ms * 60. / 1000.;
+
+        // Take the whole instructions and timer ticks from the accumulators
+        let instructions = self.tick_instruction_carry.floor();
+        self.tick_instruction_carry -= instructions;
+        let mut instructions = instructions as u32;
+
+        let timer_ticks = self.tick_timer_carry.floor();
+        self.tick_timer_carry -= timer_ticks;
+        let timer_ticks = timer_ticks as u32;
+
+        // The limit apply to every frame of the elapsed time, at least one
+        if let Some(max_instructions) = self.config.max_instructions_per_frame {
+            instructions = instructions.min(max_instructions.saturating_mul(timer_ticks.max(1)));
+        }
+
+        // Spread the timer ticks evenly between the instructions
+        let mut timers_done = 0;
+        let mut waiting_vblank = false;
+        for i in 0..instructions {
+            let timers_due = (i as u64 * timer_ticks as u64 / instructions as u64) as u32;
+            while timers_done < timers_due {
+                self.tick_timers();
+                timers_done += 1;
+                waiting_vblank = false;
+            }
+
+            // With display_wait the instructions after a draw wait for the next tick
+            if waiting_vblank {
+                continue;
+            }
+
+            self.execute_next()?;
+            waiting_vblank = self.config.display_wait && self.just_drew;
+        }
+
+        // Run the remaining timer ticks
+        while timers_done < timer_ticks {
+            self.tick_timers();
+            timers_done += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Run the given number of instructions without advancing the timers,
+    /// for tests and benchmarks independent from the wall-clock time
+    /// The timers must be driven with tick_timers
+    /// Stop at the first error
+    pub fn run_cycles(&mut self, count: u32) -> Result<(), ChipError> {
+        if self.paused {
+            return Ok(());
+        }
+
+        for _ in 0..count {
+            self.execute_next()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the given virtual duration at the given speed as fast as possible,
+    /// for benchmarks and fast-forwarding, the duration is rounded to whole
+    /// 60 Hz frames and the max_instructions_per_frame cap doesn't apply
+    /// Stop at the first error
+    pub fn run_for(&mut self, duration: Duration, instruction_per_second: u32) -> Result<(), ChipError> {
+        if self.paused {
+            return Ok(());
+        }
+
+        let frames = (duration.as_secs_f64() * 60.).round() as u64;
+        let instructions_per_frame = instruction_per_second as f64 / 60.;
+
+        let mut carry = 0.;
+        for _ in 0..frames {
+            // Carry the fraction of instruction to the next frame
+            carry += instructions_per_frame;
+            let instructions = carry.floor();
+            carry -= instructions;
+
+            for _ in 0..instructions as u64 {
+                self.execute_next()?;
+            }
+            self.tick_timers();
+        }
+
+        Ok(())
+    }
+
+    /// Run the emulator loop, execute exactly one instruction
+    /// The timers are driven separately with tick_timers
+    pub fn step(&mut self) -> Result<(), ChipError> {
+        if self.paused {
+            return Ok(());
+        }
+
+        self.execute_next()
+    }
+
+    /// Pause or resume the emulation, while paused step, run_frame, tick
+    /// and run_cycles don't run any instruction and the timers are frozen
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Return true if the emulation is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Return true if an instruction returned an error, the program exited
+    /// with 00FD or an unknown opcode halted it with the Halt policy
+    /// The machine state is no longer meaningful, while halted the run methods
+    /// don't run any instruction, they return the error that halted it if any
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Return the error that halted the emulator, Ok if not halted
+    fn halt_result(&self) -> Result<(), ChipError> {
+        self.halt_error.map_or(Ok(()), Err)
+    }
+
+    /// Run the emulator loop stopping at the breakpoints, the instruction at a
+    /// breakpoint is executed by the next call after the breakpoint was reported
+    /// Unlike step the instruction is run even while paused, for single stepping
+    pub fn step_debug(&mut self) -> Result<StepResult, ChipError> {
+        let address = self.program_counter;
+
+        // Report the breakpoint once before executing its instruction
+        if self.breakpoints.contains(&address) && self.breakpoint_hit != Some(address) {
+            self.breakpoint_hit = Some(address);
+            return Ok(StepResult::Breakpoint(address));
+        }
+
+        self.breakpoint_hit = None;
+        self.execute_next()?;
+
+        Ok(StepResult::Executed)
+    }
+
+    /// Run up to the given number of instructions until a breakpoint is reached,
+    /// the emulator is then paused so that the frame loop stop at the breakpoint
+    /// Return StepResult::Executed if no breakpoint was reached
+    pub fn continue_until_break(&mut self, limit: u32) -> Result<StepResult, ChipError> {
+        for _ in 0..limit {
+            if let StepResult::Breakpoint(address) = self.step_debug()? {
+                self.paused = true;
+                return Ok(StepResult::Breakpoint(address));
+            }
+        }
+
+        Ok(StepResult::Executed)
+    }
+
+    /// Run the emulator loop, panic if the instruction return an error
+    /// Convenience for callers that don't handle the execution errors
+    pub fn step_or_panic(&mut self) {
+        if let Err(error) = self.step() {
+            panic!("Chip-8 execution error: {}", error);
+        }
+    }
+
+    /// Fetch, decode and execute the instruction at the program counter
+    fn execute_next(&mut self) -> Result<(), ChipError> {
+        if self.halted {
+            return self.halt_result();
+        }
+
+        self.ips_tick_instructions += 1;
+        self.just_drew = false;
+
+        // Apply the replayed key state due at this cycle
+        if let Some(replay) = self.replay.as_mut() {
+            let keys = replay.keys_at(self.cycles);
+            if replay.is_finished() {
+                self.replay = None;
+            }
+
+            if let Some(keys) = keys {
+                self.set_keys(keys);
+            }
+        }
+        self.cycles += 1;
+
+        // Fetch, decode and execute the instruction
+        let address = self.program_counter;
+        let instruction = self.fetch();
+        let mut result = self
+            .check_range(address as usize, 2)
+            .and_then(|_| self.decode_execute(instruction));
+
+        if let Err(ChipError::UnknownOpcode(raw)) = result {
+            result = self.handle_unknown_opcode(address, raw);
+        }
+
+        // Halt and build the crash report if required
+        if let Err(error) = result {
+            self.halted = true;
+            self.halt_error = Some(error);
+            if self.config.crash_report {
+                self.last_crash_report = Some(self.build_crash_report(error, address, instruction));
+            }
+        }
+
+        result
+    }
+
+    /// Run the next instruction, if it's a 2NNN call keep running until
+    /// the called subroutine return to the current stack depth
+    /// Return an error if the subroutine doesn't return within a million instructions
+    pub fn step_over(&mut self) -> Result<(), ChipError> {
+        if self.paused {
+            return Ok(());
+        }
+
+        let is_call = self.read_byte(self.program_counter as usize) >> 4 == 0x02;
+        let depth = self.stack.len();
+
+        self.step()?;
+        if !is_call {
+            return Ok(());
+        }
+
+        // Run until the matching return
+        let mut instructions = 0;
+        while self.stack.len() > depth {
+            if instructions == STEP_OVER_LIMIT {
+                return Err(ChipError::StepOverLimit { limit: STEP_OVER_LIMIT });
+            }
+
+            self.step()?;
+            instructions += 1;
+        }
 
-        output
+        Ok(())
     }
 
-    /// Load a chip-8 rom from a file
-    pub fn load_rom(&mut self, file_path: &str) -> Result<()> {
-        const START_ADDRESS: usize = 0x200;
+    /// Decode and execute the given instruction immediately, without reading it
+    /// from memory and without advancing the program counter unless the instruction
+    /// itself change it (jumps, calls, skips)
+    /// All the side effects (registers, memory, display) apply to the real state,
+    /// a FX0A instruction without a released key doesn't block and has no effect,
+    /// a 00FD instruction halt the emulator leaving the program counter unchanged
+    /// Unknown opcodes follow the unknown opcode policy as in step,
+    /// other errors are returned without halting the emulator
+    pub fn execute_opcode(&mut self, bytes: [u8; 2]) -> Result<(), ChipError> {
+        if self.halted {
+            return self.halt_result();
+        }
 
-        // Open the rom file
-        let path = Path::new(file_path);
-        let mut f = File::open(path)?;
-        let size = f.metadata()?.len() as usize;
+        let address = self.program_counter;
+        let instruction = ChipInstruction::new(bytes);
 
-        // Create memory slice and read the buffer in it
-        let memory_slice = &mut self.memory[START_ADDRESS..START_ADDRESS + size];
-        f.read_exact(memory_slice)?;
+        // Blocking key waits and exits move the program counter back to the
+        // instruction just fetched, so the fetch is simulated for them
+        let stay = instruction.opcode() == 0x00FD
+            || (instruction.op_code == 0x0F && instruction.nn() == 0x0A);
+        if stay {
+            self.program_counter = address.wrapping_add(2);
+        }
 
-        // Set the program counter to the rom start address
-        self.program_counter = START_ADDRESS as u16;
+        let mut result = self.decode_execute(instruction);
+        if stay {
+            self.program_counter = address;
+        }
 
-        Ok(())
+        if let Err(ChipError::UnknownOpcode(raw)) = result {
+            result = self.handle_unknown_opcode(address, raw);
+        }
+
+        result
     }
 
-    /// Wait for the right amount of time to start the next clock cycle
-    pub fn get_cycle_duration(&self) -> Duration {
-        Duration::from_secs_f64(1. / self.config.instruction_per_second as f64)
+    /// Return the report of the last error returned by step
+    /// Only available if the crash_report setting is enabled
+    pub fn crash_report(&self) -> Option<&ChipCrashReport> {
+        self.last_crash_report.as_ref()
     }
 
-    /// Run the emulator loop
-    pub fn step(&mut self) {
-        // Decrements the timers
-        self.update_timer();
+    /// Run a single 60 Hz timer tick, decrementing the delay and sound timers
+    /// Called once per frame by run_frame, callers of step
+    /// and run_cycles must drive the timers themselves
+    pub fn tick_timers(&mut self) {
+        if self.paused {
+            return;
+        }
 
-        // Fetch, decode and execute the instruction
-        let instruction = self.fetch();
-        self.decode_execute(instruction);
-    }
+        self.frame_count += 1;
 
-    /// Decrements the delay and sound timer 60 times per seconds
-    fn update_timer(&mut self) {
-        if self.last_timer_update.elapsed() >= Duration::from_secs_f64(1. / 60.) {
-            // Decrements timers if they are greater that 0
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
-            }
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
-            }
+        // Decrements timers if they are greater that 0
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
 
-            // Update last update timer
-            self.last_timer_update = Instant::now();
+            // Notify the end of the sound
+            if self.sound_timer == 0 {
+                self.emit_event(ChipEvent::SoundTimerExpired { frame: self.frame_count });
+            }
+        }
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
         }
+
+        self.measure_ips();
     }
 
     /// Fetch an 16 bit instruction at the program counter address
     /// and increment it by 2
     /// Return a Chip Instruction struct
     fn fetch(&mut self) -> ChipInstruction {
+        // Notify the first instruction fetched outside of the rom
+        let in_rom = self.rom_range().contains(&(self.program_counter as usize));
+        if !in_rom && !self.outside_rom {
+            self.emit_event(ChipEvent::LeftRom { address: self.program_counter });
+        }
+        self.outside_rom = !in_rom;
+
         // Read the instruction from memory
         let instruction_array = [
-            self.memory[self.program_counter as usize],
-            self.memory[self.program_counter as usize + 1],
+            self.read_byte(self.program_counter as usize),
+            self.read_byte(self.program_counter as usize + 1),
         ];
         // Increment the program counter
-        self.program_counter += 2;
+        self.program_counter = self.program_counter.wrapping_add(2);
 
         ChipInstruction::new(instruction_array)
     }
 
     /// Decode and execute the given instruction
-    fn decode_execute(&mut self, instruction: ChipInstruction) {
+    fn decode_execute(&mut self, instruction: ChipInstruction) -> Result<(), ChipError> {
         // Decode the instruction with a match statement
         match (instruction.op_code, instruction.parameter) {
             // Clear the screen
             (0x00, [0x00, 0x0E, 0x00]) => {
-                self.video_buffer = [0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize];
-                self.buffer_updated = true;
+                self.clear_screen();
             }
 
             // Jump instruction
             (0x01, [x, _, _]) => {
                 let address = u16::from_be_bytes([x, instruction.raw[1]]);
 
-                self.program_counter = address;
+                self.program_counter = self.jump_target(address);
             }
             // Jump and push current PC to stack
             (0x02, [x, _, _]) => {
                 let address = u16::from_be_bytes([x, instruction.raw[1]]);
 
+                if self.stack.len() >= self.config.max_stack_depth {
+                    return Err(ChipError::StackOverflow { depth: self.config.max_stack_depth });
+                }
+
                 self.stack.push(self.program_counter);
-                self.program_counter = address;
+                self.program_counter = self.jump_target(address);
             }
             // Jump with offset register
             (0x0B, [x, _, _]) => {
@@ -284,11 +1831,45 @@ impl ChipEmulator {
                     self.registers[0]
                 };
 
-                self.program_counter = address + reg_offset as u16;
+                self.program_counter = self.jump_target(address + reg_offset as u16);
             }
             // Pop an address from the stack and set the PC to it
             (0x00, [0x00, 0x0E, 0x0E]) => {
-                self.program_counter = self.stack.pop().expect("Tried to pop an empty stack");
+                self.program_counter = self.stack.pop().ok_or(ChipError::StackUnderflow)?;
+            }
+
+            // SUPER-CHIP scroll down N pixels, right 4 pixels and left 4 pixels
+            (0x00, [0x00, 0x0C, n]) if self.supports(InstructionSet::SuperChip) => {
+                self.scroll(0, n as isize);
+            }
+            // XO-CHIP scroll up N pixels
+            (0x00, [0x00, 0x0D, n]) if self.supports(InstructionSet::XoChip) => {
+                self.scroll(0, -(n as isize));
+            }
+            (0x00, [0x00, 0x0F, 0x0B]) if self.supports(InstructionSet::SuperChip) => {
+                self.scroll(4, 0);
+            }
+            (0x00, [0x00, 0x0F, 0x0C]) if self.supports(InstructionSet::SuperChip) => {
+                self.scroll(-4, 0);
+            }
+
+            // SUPER-CHIP exit the interpreter, the program counter stay on the
+            // instruction so the machine keep executing it until the frontend stop
+            (0x00, [0x00, 0x0F, 0x0D]) if self.supports(InstructionSet::SuperChip) => {
+                self.program_counter = self.program_counter.wrapping_sub(2);
+
+                if !self.halted {
+                    self.halted = true;
+                    self.emit_event(ChipEvent::Exit { address: self.program_counter });
+                }
+            }
+
+            // SUPER-CHIP low and high resolution mode
+            (0x00, [0x00, 0x0F, 0x0E]) if self.supports(InstructionSet::SuperChip) => {
+                self.set_resolution(false);
+            }
+            (0x00, [0x00, 0x0F, 0x0F]) if self.supports(InstructionSet::SuperChip) => {
+                self.set_resolution(true);
             }
 
             // 3XNN Skip instruction if value in registers X is equal to NN
@@ -296,7 +1877,7 @@ impl ChipEmulator {
                 let register_value = self.registers[x as usize];
 
                 if register_value == instruction.raw[1] {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             // 3XNN Skip instruction if value in registers X is not equal to NN
@@ -304,7 +1885,7 @@ impl ChipEmulator {
                 let register_value = self.registers[x as usize];
 
                 if register_value != instruction.raw[1] {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             // 5XY0 Skip instruction if value in registers X is equal to
@@ -314,7 +1895,25 @@ impl ChipEmulator {
                 let register_value_y = self.registers[y as usize];
 
                 if register_value_x == register_value_y {
-                    self.program_counter += 2;
+                    self.skip_next();
+                }
+            }
+            // 5XY2 Store the registers from X to Y in continuous memory
+            // starting from the address in the index pointer
+            (0x05, [x, y, 0x02]) if self.supports(InstructionSet::XoChip) => {
+                self.check_range(self.index_pointer as usize, x.abs_diff(y) as usize + 1)?;
+                for (i, register) in Self::register_range(x, y).enumerate() {
+                    let address = (self.index_pointer as usize + i) % self.memory.len();
+                    self.write_byte(address, self.registers[register]);
+                }
+            }
+            // 5XY3 Load the registers from X to Y from continuous memory
+            // starting from the address in the index pointer
+            (0x05, [x, y, 0x03]) if self.supports(InstructionSet::XoChip) => {
+                self.check_range(self.index_pointer as usize, x.abs_diff(y) as usize + 1)?;
+                for (i, register) in Self::register_range(x, y).enumerate() {
+                    let address = (self.index_pointer as usize + i) % self.memory.len();
+                    self.registers[register] = self.read_byte(address);
                 }
             }
             // 9XY0 Skip instruction if value in registers X is not equal to
@@ -324,13 +1923,13 @@ impl ChipEmulator {
                 let register_value_y = self.registers[y as usize];
 
                 if register_value_x != register_value_y {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
 
             // Logical and mathematical instructions
             (0x08, parameter) => {
-                self.alu(parameter);
+                self.alu(parameter)?;
             }
 
             // Set the register in parameter 0 to the value in raw 1
@@ -352,7 +1951,7 @@ impl ChipEmulator {
 
             // Generate a random number and mask it
             (0x0C, [x, _, _]) => {
-                let random_number: u8 = thread_rng().gen();
+                let random_number: u8 = self.rng.gen();
 
                 self.registers[x as usize] = random_number & instruction.raw[1];
             }
@@ -368,6 +1967,38 @@ impl ChipEmulator {
             // Set the sound timer to the value in the register X
             (0x0F, [x, 0x01, 0x08]) => {
                 self.sound_timer = self.registers[x as usize];
+                self.sound_timer_reloaded = true;
+            }
+
+            // F000 NNNN Set the index pointer to the 16 bit address in the next word
+            (0x0F, [0x00, 0x00, 0x00]) if self.supports(InstructionSet::XoChip) => {
+                let address = self.program_counter as usize;
+                self.check_range(address, 2)?;
+                self.index_pointer = u16::from_be_bytes([
+                    self.read_byte(address),
+                    self.read_byte(address + 1),
+                ]);
+
+                self.program_counter = self.program_counter.wrapping_add(2);
+            }
+
+            // FN01 Select the drawing planes used by the draw, clear and scroll instructions
+            (0x0F, [n, 0x00, 0x01]) if self.supports(InstructionSet::XoChip) => {
+                self.planes = n & 0b11;
+            }
+            // F002 Load the 16 bytes audio pattern starting from the index pointer
+            (0x0F, [0x00, 0x00, 0x02]) if self.supports(InstructionSet::XoChip) => {
+                self.check_range(self.index_pointer as usize, 16)?;
+                let mut pattern = [0u8; 16];
+                for (i, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_byte((self.index_pointer as usize + i) % self.memory.len());
+                }
+
+                self.audio_pattern = Some(pattern);
+            }
+            // FX3A Set the pitch register to the value in register X
+            (0x0F, [x, 0x03, 0x0A]) if self.supports(InstructionSet::XoChip) => {
+                self.pitch = self.registers[x as usize];
             }
 
             // Add the value in register X to the index register
@@ -376,53 +2007,82 @@ impl ChipEmulator {
                 let value_x = self.registers[x as usize];
 
                 // Set index pointer and VF register
-                self.index_pointer += value_x as u16;
+                self.index_pointer = self.index_pointer.wrapping_add(value_x as u16);
                 self.registers[0x0F] = if self.index_pointer >= 0x1000 { 1 } else { 0 };
             }
 
             // FX55 Store the value of all the register from 0 to X in
             // continuous memory starting from the address in the index pointer
             (0x0F, [x, 0x05, 0x05]) => {
+                self.check_range(self.index_pointer as usize, x as usize + 1)?;
                 for i in 0..=x {
                     let i = i as usize;
-                    self.memory[self.index_pointer as usize + i] = self.registers[i];
+                    self.write_byte(self.index_pointer as usize + i, self.registers[i]);
                 }
+
+                self.store_load_update_index(x);
             }
             // FX65 Load the value of all the register from 0 to X from
             // continuous memory starting from the address in the index pointer
             (0x0F, [x, 0x06, 0x05]) => {
+                self.check_range(self.index_pointer as usize, x as usize + 1)?;
                 for i in 0..=x {
                     let i = i as usize;
-                    self.registers[i] = self.memory[self.index_pointer as usize + i];
+                    self.registers[i] = self.read_byte(self.index_pointer as usize + i);
                 }
+
+                self.store_load_update_index(x);
             }
 
-            // Block the execution until a key press occur
+            // Block the execution until a key is pressed and released
             // and save the value in register X
             (0x0F, [x, 0x00, 0x0A]) => {
-                if let Some(key) = self.key {
-                    self.registers[x as usize] = key as u8;
-                } else {
-                    self.program_counter -= 2;
+                let keys = self.pressed_keys();
+
+                // Store the pressed key with the lowest value immediately
+                if !self.config.key_wait_release {
+                    if keys != 0 {
+                        self.registers[x as usize] = keys.trailing_zeros() as u8;
+                        self.tapped_keys = 0;
+                    } else {
+                        self.program_counter = self.program_counter.wrapping_sub(2);
+                    }
+
+                    return Ok(());
+                }
+
+                match self.wait_key {
+                    // The awaited key was released
+                    Some(key) if keys & (1 << key) == 0 => {
+                        self.registers[x as usize] = key;
+                        self.wait_key = None;
+                    }
+                    Some(_) => self.program_counter = self.program_counter.wrapping_sub(2),
+                    None => {
+                        // Wait for the release of the pressed key with the lowest value
+                        if keys != 0 {
+                            self.wait_key = Some(keys.trailing_zeros() as u8);
+                            self.tapped_keys = 0;
+                        }
+                        self.program_counter = self.program_counter.wrapping_sub(2);
+                    }
                 }
             }
             // Skip the next instruction if the key in the register VX is being press
             (0x0E, [x, 0x09, 0x0E]) => {
-                if let Some(key) = self.key {
-                    if self.registers[x as usize] == key as u8 {
-                        self.program_counter += 2;
-                    }
+                let key = ChipKey::try_from(self.registers[x as usize]);
+                if key.is_ok_and(|key| self.is_key_pressed(key)) {
+                    self.skip_next();
                 }
+                self.tapped_keys = 0;
             }
             // Skip the next instruction if the key in the register VX is not being press
             (0x0E, [x, 0x0A, 0x01]) => {
-                if let Some(key) = self.key {
-                    if self.registers[x as usize] != key as u8 {
-                        self.program_counter += 2;
-                    }
-                } else {
-                    self.program_counter += 2;
+                let key = ChipKey::try_from(self.registers[x as usize]);
+                if !key.is_ok_and(|key| self.is_key_pressed(key)) {
+                    self.skip_next();
                 }
+                self.tapped_keys = 0;
             }
 
             // Set the index register at the font address of the char in VX
@@ -431,10 +2091,28 @@ impl ChipEmulator {
 
                 self.index_pointer = FONT_ADDRESS as u16 + (char as u16) * 5;
             }
+            // FX30 Set the index pointer to the big font character in the register X
+            (0x0F, [x, 0x03, 0x00]) if self.supports(InstructionSet::SuperChip) => {
+                let char = self.registers[x as usize] & 0x0F;
+
+                self.index_pointer = BIG_FONT_ADDRESS as u16 + (char as u16) * 10;
+            }
+            // FX75 Store the registers from 0 to X in the flag registers
+            (0x0F, [x, 0x07, 0x05]) if self.supports(InstructionSet::SuperChip) => {
+                let len = x as usize + 1;
+                self.rpl_flags[..len].copy_from_slice(&self.registers[..len]);
+            }
+            // FX85 Load the registers from 0 to X from the flag registers
+            (0x0F, [x, 0x08, 0x05]) if self.supports(InstructionSet::SuperChip) => {
+                let len = x as usize + 1;
+                self.registers[..len].copy_from_slice(&self.rpl_flags[..len]);
+            }
+
             // Store each digit of the decimal number stored in the VX register
             // in 3 byte of continuous memory starting from the index pointer
             (0x0F, [x, 0x03, 0x03]) => {
                 let number = self.registers[x as usize];
+                self.check_range(self.index_pointer as usize, 3)?;
 
                 let digits = [
                     number / 100,
@@ -444,23 +2122,265 @@ impl ChipEmulator {
 
                 // Write digit into memory
                 for (i, digit) in digits.iter().enumerate() {
-                    self.memory[self.index_pointer as usize + i] = *digit;
+                    self.write_byte(self.index_pointer as usize + i, *digit);
                 }
             }
 
             // Display draw instruction
             (0x0D, _) => {
-                self.draw(instruction.parameter);
+                self.last_draw_info = self.draw(instruction.parameter)?;
+                self.just_drew = true;
             }
 
             _ => {
-                println!("Unrecognized instruction: {:?}", instruction);
+                return Err(ChipError::UnknownOpcode(instruction.raw));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return true if the configured instruction set include the given one
+    fn supports(&self, instruction_set: InstructionSet) -> bool {
+        self.config.instruction_set >= instruction_set
+    }
+
+    /// Return the indices of the registers from X to Y included,
+    /// in descending order if X is greater than Y
+    fn register_range(x: u8, y: u8) -> impl Iterator<Item = usize> {
+        let (x, y) = (x as usize, y as usize);
+
+        (0..=x.abs_diff(y)).map(move |i| if x <= y { x + i } else { x - i })
+    }
+
+    /// Set the held keys, recording the change if a recording is in progress
+    fn set_keys(&mut self, keys: u16) {
+        if keys == self.keys {
+            return;
+        }
+        self.keys = keys;
+
+        if let Some(recording) = self.recording.as_mut() {
+            recording.events.push(InputEvent { cycle: self.cycles, keys });
+        }
+    }
+
+    /// Return the keys either held or tapped as a bitmask
+    fn pressed_keys(&self) -> u16 {
+        self.keys | self.tapped_keys
+    }
+
+    /// Read a byte of memory, from a peripheral if one is mapped at the address
+    /// The address wrap around at the end of memory
+    fn read_byte(&mut self, address: usize) -> u8 {
+        let address = address % self.memory.len();
+        if let Some(peripheral) = self.peripheral_at(address) {
+            return (peripheral.read)(address as u16);
+        }
+
+        self.memory[address]
+    }
+
+    /// Write a byte of memory, to a peripheral if one is mapped at the address
+    /// The address wrap around at the end of memory
+    fn write_byte(&mut self, address: usize, value: u8) {
+        let address = address % self.memory.len();
+        if let Some(peripheral) = self.peripheral_at(address) {
+            (peripheral.write)(address as u16, value);
+            return;
+        }
+
+        self.memory[address] = value;
+    }
+
+    /// Return the peripheral mapped at the given address if any
+    fn peripheral_at(&mut self, address: usize) -> Option<&mut Peripheral> {
+        if self.peripherals.is_empty() {
+            return None;
+        }
+
+        self.peripherals
+            .iter_mut()
+            .find(|peripheral| peripheral.range.contains(&(address as u16)))
+    }
+
+    /// Apply the unknown opcode policy to the given opcode found at the given address
+    fn handle_unknown_opcode(&mut self, address: u16, raw: [u8; 2]) -> Result<(), ChipError> {
+        // Warn about the first extension opcode not enabled in the configuration
+        let opcode = u16::from_be_bytes(raw);
+        self.warn_extension_opcode(address, opcode);
+
+        match self.config.unknown_opcode_policy {
+            UnknownOpcodePolicy::Ignore => Ok(()),
+            UnknownOpcodePolicy::Event => {
+                self.emit_event(ChipEvent::UnknownOpcode { address, opcode });
+                Ok(())
+            }
+            UnknownOpcodePolicy::Halt => {
+                self.program_counter = address;
+                self.halted = true;
+                Ok(())
+            }
+            UnknownOpcodePolicy::Error => Err(ChipError::UnknownOpcode(raw)),
+        }
+    }
+
+    /// Emit an extension opcode event if the opcode belong to an instruction set
+    /// not enabled in the configuration, only once per emulator
+    fn warn_extension_opcode(&mut self, address: u16, opcode: u16) {
+        if self.extension_warned {
+            return;
+        }
+
+        let Some(profile) = opcodes::extension_profile(opcode) else {
+            return;
+        };
+
+        if !self.supports(profile.instruction_set()) {
+            self.extension_warned = true;
+            self.emit_event(ChipEvent::ExtensionOpcode { address, opcode, profile });
+        }
+    }
+
+    /// Call the event hook with the given event if one is set
+    fn emit_event(&mut self, event: ChipEvent) {
+        if let Some(hook) = self.event_hook.as_mut() {
+            hook(event);
+        }
+    }
+
+    /// Clear the pixels of the current resolution on the selected planes
+    fn clear_screen(&mut self) {
+        let (width, height) = self.get_screen_size();
+        let len = (width * height) as usize;
+
+        if self.planes == 0b11 || !self.supports(InstructionSet::XoChip) {
+            self.video_buffer.clear(len);
+        } else {
+            for i in 0..len {
+                self.video_buffer.set(i, self.video_buffer.get(i) & !self.planes);
+            }
+        }
+        self.buffer_updated = true;
+    }
+
+    /// Return the planes affected by the draw, clear and scroll instructions,
+    /// only the first plane exist without XO-CHIP
+    fn active_planes(&self) -> u8 {
+        if self.supports(InstructionSet::XoChip) {
+            self.planes
+        } else {
+            1
+        }
+    }
+
+    /// Change the display resolution mode
+    /// If the screen is not cleared the pixels keep their coordinates,
+    /// the ones outside of the new resolution are discarded
+    fn set_resolution(&mut self, hires: bool) {
+        if self.hires == hires {
+            return;
+        }
+
+        let (old_width, old_height) = self.get_screen_size();
+        let old_buffer = self.video_buffer.clone();
+        self.hires = hires;
+
+        let (width, height) = self.get_screen_size();
+        self.video_buffer.clear((HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT) as usize);
+
+        if !self.config.clear_on_resolution_change {
+            let copy_width = width.min(old_width) as usize;
+            let copy_height = height.min(old_height) as usize;
+
+            for y in 0..copy_height {
+                let old_row = y * old_width as usize;
+                let row = y * width as usize;
+
+                for x in 0..copy_width {
+                    self.video_buffer.set(row + x, old_buffer.get(old_row + x));
+                }
             }
         }
+
+        self.buffer_updated = true;
+    }
+
+    /// Move the pixels of the current resolution on the selected planes by the
+    /// given offset, the pixels moved off screen are discarded and the vacated ones cleared
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.get_screen_size();
+        let (width, height) = (width as isize, height as isize);
+        let old_buffer = self.video_buffer.clone();
+        let planes = self.active_planes();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (old_x, old_y) = (x - dx, y - dy);
+
+                let moved = if (0..width).contains(&old_x) && (0..height).contains(&old_y) {
+                    old_buffer.get((old_y * width + old_x) as usize) & planes
+                } else {
+                    0
+                };
+
+                // The planes not selected are left in place
+                let index = (y * width + x) as usize;
+                let pixel = moved | (old_buffer.get(index) & !planes);
+                self.video_buffer.set(index, pixel);
+            }
+        }
+
+        self.buffer_updated = true;
+    }
+
+    /// Skip the next instruction, the four bytes F000 NNNN instruction is
+    /// skipped entirely when XO-CHIP is supported
+    fn skip_next(&mut self) {
+        let address = self.program_counter as usize;
+        let long_load = self.read_byte(address) == 0xF0 && self.read_byte(address + 1) == 0x00;
+
+        if long_load && self.supports(InstructionSet::XoChip) {
+            self.program_counter = self.program_counter.wrapping_add(4);
+        } else {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    /// Return the program counter value for a jump to the given address
+    /// masked to an even address if the align_pc setting is enabled
+    fn jump_target(&self, address: u16) -> u16 {
+        if self.config.align_pc {
+            address & !1
+        } else {
+            address
+        }
+    }
+
+    /// In strict mode return an error if the given number of bytes starting
+    /// at the address don't fit in memory, otherwise the accesses wrap around
+    fn check_range(&self, address: usize, len: usize) -> Result<(), ChipError> {
+        if self.config.strict && address + len > self.memory.len() {
+            return Err(ChipError::MemoryOutOfBounds { address: address.max(self.memory.len()) });
+        }
+
+        Ok(())
+    }
+
+    /// Update the index pointer after a FX55 or FX65 instruction
+    /// according to the store_load_index setting
+    fn store_load_update_index(&mut self, x: u8) {
+        let increment = match self.config.store_load_index {
+            StoreLoadIndex::IncrementXPlusOne => x as u16 + 1,
+            StoreLoadIndex::IncrementX => x as u16,
+            StoreLoadIndex::Unchanged => 0,
+        };
+
+        self.index_pointer = self.index_pointer.wrapping_add(increment);
     }
 
     /// Perform logical and mathematical functions
-    fn alu(&mut self, parameter: [u8; 3]) {
+    fn alu(&mut self, parameter: [u8; 3]) -> Result<(), ChipError> {
         // Match the alu instruction
         match parameter[2] {
             // XY0 Set register X to the value of register Y
@@ -473,6 +2393,7 @@ impl ChipEmulator {
                 let value_y = self.registers[parameter[1] as usize];
 
                 self.registers[parameter[0] as usize] = value_x | value_y;
+                self.logic_reset_vf();
             }
             // XY2 Set register X to (register Y & registers X)
             0x02 => {
@@ -480,6 +2401,7 @@ impl ChipEmulator {
                 let value_y = self.registers[parameter[1] as usize];
 
                 self.registers[parameter[0] as usize] = value_x & value_y;
+                self.logic_reset_vf();
             }
             // XY3 Set register X to (register Y ^ registers X)
             0x03 => {
@@ -487,6 +2409,7 @@ impl ChipEmulator {
                 let value_y = self.registers[parameter[1] as usize];
 
                 self.registers[parameter[0] as usize] = value_x ^ value_y;
+                self.logic_reset_vf();
             }
             // XY4 Set register X to (register Y + registers X)
             // set register F to 1 if an overflow occur to 0 if it doesn't
@@ -555,71 +2478,332 @@ impl ChipEmulator {
             }
 
             _ => {
-                println!("Unrecognized alu instruction");
+                let raw = [0x80 | parameter[0], parameter[1] << 4 | parameter[2]];
+                return Err(ChipError::UnknownOpcode(raw));
             }
         }
+
+        Ok(())
+    }
+
+    /// Apply the configured VF behavior after a logical instruction
+    fn logic_reset_vf(&mut self) {
+        if self.config.logic_vf == LogicVfBehavior::ResetToZero {
+            self.registers[0x0F] = 0;
+        }
     }
 
     /// Draw the sprite to the index pointer address to the screen with an xor operation
-    fn draw(&mut self, parameter: [u8; 3]) {
+    /// In high resolution mode DXY0 draw a 16x16 sprite made of two bytes per row
+    /// Return the number of pixels toggled by the draw
+    fn draw(&mut self, parameter: [u8; 3]) -> Result<DrawInfo, ChipError> {
         // Decode the parameter
-        let rows = parameter[2] as usize;
+        let big_sprite = parameter[2] == 0 && self.hires && self.supports(InstructionSet::SuperChip);
+        let (rows, sprite_width) = if big_sprite {
+            (16, 16)
+        } else {
+            (parameter[2] as usize, 8)
+        };
+        let row_bytes = sprite_width / 8;
+
+        let (width, height) = self.get_screen_size();
+        let (width, height) = (width as usize, height as usize);
+
+        let sprite_x = self.registers[parameter[0] as usize] as usize % width;
+        let sprite_y = self.registers[parameter[1] as usize] as usize % height;
+
+        let mut info = DrawInfo::default();
+
+        // With both XO-CHIP planes selected the sprite of the second plane
+        // follow the one of the first plane in memory
+        let planes = self.active_planes();
+        let sprite_len = rows * row_bytes;
+        let mut sprite_start = self.index_pointer as usize;
+
+        for plane in [0b01, 0b10] {
+            if planes & plane == 0 {
+                continue;
+            }
+
+            self.draw_plane(plane, sprite_start, (sprite_x, sprite_y), (rows, sprite_width), &mut info)?;
+            sprite_start += sprite_len;
+        }
+
+        // Set VF register to 1 if a collision occurred
+        self.registers[0x0F] = info.collision as u8;
+
+        // Record the collision for the current frame
+        if info.collision {
+            self.collision_this_frame = true;
+        }
+
+        // Change the value of buffer updated
+        self.buffer_updated = true;
+
+        Ok(info)
+    }
+
+    /// Draw the sprite at the given address on the given plane,
+    /// recording the toggled pixels in the draw info
+    fn draw_plane(
+        &mut self,
+        plane: u8,
+        sprite_address: usize,
+        (sprite_x, sprite_y): (usize, usize),
+        (rows, sprite_width): (usize, usize),
+        info: &mut DrawInfo,
+    ) -> Result<(), ChipError> {
+        let row_bytes = sprite_width / 8;
+
+        let (width, height) = self.get_screen_size();
+        let (width, height) = (width as usize, height as usize);
+
+        // Get the sprite slice, clamped to the end of memory so that
+        // only the available rows are drawn
+        let sprite_start = sprite_address.min(self.memory.len());
+        let sprite_end = (sprite_start + rows * row_bytes).min(self.memory.len());
+        let available = (sprite_end - sprite_start) / row_bytes;
+
+        // Report the missing rows
+        if available < rows {
+            if self.config.strict {
+                return Err(ChipError::MemoryOutOfBounds { address: self.memory.len() });
+            }
 
-        let sprite_x = (self.registers[parameter[0] as usize] % 64) as usize;
-        let sprite_y = (self.registers[parameter[1] as usize] % 32) as usize;
+            self.emit_event(ChipEvent::SpriteClamped {
+                address: sprite_address as u16,
+                rows: rows as u8,
+                available: available as u8,
+            });
+        }
 
-        // Get sprite and display buffer slices
-        let sprite = &self.memory[self.index_pointer as usize..self.index_pointer as usize + rows];
+        // Read the sprite rows, left aligned in 16 bits
+        let mut sprite = [0u16; 16];
+        let sprite = &mut sprite[..available];
+        for (i, sprite_row) in sprite.iter_mut().enumerate() {
+            let address = sprite_start + i * row_bytes;
 
-        // Set VF register to 0
-        self.registers[0x0F] = 0;
+            *sprite_row = (self.read_byte(address) as u16) << 8;
+            if row_bytes == 2 {
+                *sprite_row |= self.read_byte(address + 1) as u16;
+            }
+        }
 
         for (row, sprite_row) in sprite.iter().enumerate() {
-            // Calculate y and check for overflow
-            let y = sprite_y + row;
-            if y >= 32 {
-                break;
+            // Calculate y, wrap or clip the rows past the bottom edge
+            let mut y = sprite_y + row;
+            if y >= height {
+                if !self.config.wrap_sprites {
+                    break;
+                }
+                y %= height;
+            }
+
+            // Index of the first pixel of the row in the video buffer
+            let row_base = width * y;
+
+            // Fast path: the sprite row is fully on screen,
+            // no wrapping is required and the pixels are bytes
+            if sprite_x + sprite_width <= width {
+                if let Some(bytes) = self.video_buffer.bytes_mut() {
+                    let pixels = &mut bytes[row_base + sprite_x..row_base + sprite_x + sprite_width];
+
+                    for (bit_index, pixel) in pixels.iter_mut().enumerate() {
+                        let sprite_pixel = ((sprite_row >> (15 - bit_index)) & 1) as u8 * plane;
+                        info.toggle(pixel, sprite_pixel);
+                    }
+
+                    continue;
+                }
             }
 
-            // For every bit in one of the sprite byte update one pixel
-            for bit_index in 0..8 {
-                // Calculate x and check for overflow
-                let x = (sprite_x + bit_index) % 64;
+            // For every bit in one of the sprite row update one pixel
+            for bit_index in 0..sprite_width {
+                // Calculate x, wrap or clip the pixels past the right edge
+                let mut x = sprite_x + bit_index;
+                if x >= width {
+                    if !self.config.wrap_sprites {
+                        break;
+                    }
+                    x %= width;
+                }
 
                 // Get sprite and screen pixel values
-                let sprite_pixel = (sprite_row >> (7 - bit_index)) & 0b00000001;
-                let pixel = &mut self.video_buffer[SCREEN_WIDTH as usize * y + x];
+                let sprite_pixel = ((sprite_row >> (15 - bit_index)) & 1) as u8 * plane;
+                let mut pixel = self.video_buffer.get(row_base + x);
 
-                // If the sprite and screen pixel are both on
-                // turn off the screen pixel and set VF to 1
-                // If the sprite pixel is on and the screen pixel is off
-                // turn on the screen pixel
-                self.registers[0x0F] |= sprite_pixel & *pixel;
-                *pixel = sprite_pixel ^ *pixel;
+                info.toggle(&mut pixel, sprite_pixel);
+                self.video_buffer.set(row_base + x, pixel);
             }
         }
 
-        // Change the value of buffer updated
-        self.buffer_updated = true;
+        Ok(())
     }
 }
 
 // Implement debug methods
 impl ChipEmulator {
+    /// Stop step_debug when the program counter reach the given address
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Remove the breakpoint at the given address
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.remove(&address);
+    }
+
+    /// Return the value of the V0 to VF registers
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    /// Return the address of the next instruction
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// Return the value of the index pointer
+    pub fn index_pointer(&self) -> u16 {
+        self.index_pointer
+    }
+
+    /// Return the return addresses on the stack, from the oldest call
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    /// Return the given range of memory, None if the range is out of memory
+    /// The peripherals are bypassed
+    pub fn memory_slice(&self, range: Range<usize>) -> Option<&[u8]> {
+        self.memory.get(range)
+    }
+
+    /// Return the whole program memory, the peripherals are bypassed
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Set the value of the register X, only the low nibble of x is used
+    pub fn set_register(&mut self, x: u8, value: u8) {
+        self.registers[(x & 0x0F) as usize] = value;
+    }
+
+    /// Return an exact hash of the current video buffer (64 bit FNV-1a),
+    /// any pixel difference produce a different hash
+    pub fn framebuffer_hash(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xCBF29CE484222325;
+        const FNV_PRIME: u64 = 0x00000100000001B3;
+
+        let (width, height) = self.get_screen_size();
+        self.video_buffer
+            .pixels((width * height) as usize)
+            .fold(FNV_OFFSET, |hash, pixel| (hash ^ pixel as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    /// Return a perceptual hash of the current video buffer
+    /// The screen is divided in 8x8 blocks, every bit of the hash is set
+    /// if the block has more pixels on than the average block
+    /// Similar frames have hashes with a small hamming distance,
+    /// computed with (hash_a ^ hash_b).count_ones()
+    pub fn framebuffer_phash(&self) -> u64 {
+        let (width, height) = self.get_screen_size();
+        let (block_width, block_height) = (width as usize / 8, height as usize / 8);
+
+        // Count the pixels on in every block
+        let mut blocks = [0u32; 64];
+        for (i, pixel) in self.video_buffer.pixels((width * height) as usize).enumerate() {
+            let x = i % width as usize / block_width;
+            let y = i / width as usize / block_height;
+
+            blocks[y * 8 + x] += (pixel != 0) as u32;
+        }
+
+        // Set the bits of the blocks above the average
+        let total: u32 = blocks.iter().sum();
+        blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count * 64 > total)
+            .fold(0, |hash, (i, _)| hash | 1 << i)
+    }
+
+    /// Return an iterator over the call stack from the most recent call
+    /// to the oldest one
+    pub fn call_stack(&self) -> impl Iterator<Item = StackFrame> + '_ {
+        // The program counter was already incremented when pushed on the stack
+        self.stack.iter().rev().map(|return_address| StackFrame {
+            call_address: return_address.wrapping_sub(2),
+            return_address: *return_address,
+        })
+    }
+
+    /// Render the current video buffer as a multi-line string,
+    /// using '#' for the pixels on and '.' for the pixels off
+    pub fn framebuffer_ascii(&self) -> String {
+        let (width, height) = self.get_screen_size();
+        let pixels = self.unpacked_frame();
+        let mut output = String::with_capacity(pixels.len() + height as usize);
+
+        for row in pixels.chunks(width as usize) {
+            for pixel in row {
+                output.push(if *pixel != 0 { '#' } else { '.' });
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
     /// Print the content of a specific memory range for debug purposes
     pub fn print_memory(&self, from: usize, to: usize, width: u32) {
-        for (i, value) in self.memory[from..=to].iter().enumerate() {
+        println!("{}", self.format_memory(from, to, width));
+    }
+
+    /// Format the content of a specific memory range,
+    /// every line start with the address and contain width values
+    pub fn format_memory(&self, from: usize, to: usize, width: u32) -> String {
+        let mut output = String::new();
+
+        // Out of range memory is formatted as an empty string
+        let memory = self.memory_slice(from..to + 1).unwrap_or(&[]);
+
+        for (i, value) in memory.iter().enumerate() {
             // New line if the line len is grater that width
             if i % width as usize == 0 {
-                // Print address and new line
-                print!("\n0x{:02X}:  ", from + i);
+                // Address and new line
+                output.push_str(&format!("\n0x{:02X}:  ", from + i));
             }
 
-            // Print value
-            print!("0x{:02X}  ", value);
+            // Value
+            output.push_str(&format!("0x{:02X}  ", value));
         }
 
-        // Final end line
-        println!();
+        output
+    }
+
+    /// Build a report of the current machine state for the given error
+    fn build_crash_report(
+        &self,
+        error: ChipError,
+        address: u16,
+        instruction: ChipInstruction,
+    ) -> ChipCrashReport {
+        // Memory window aligned on 8 bytes around the instruction
+        let from = (address as usize).saturating_sub(16) & !0x07;
+        let to = (address as usize + 16).min(self.memory.len() - 1);
+
+        ChipCrashReport {
+            error,
+            address,
+            instruction: instruction.raw,
+            disassembly: disasm::disassemble_instruction(instruction.raw),
+
+            registers: self.registers,
+            index_pointer: self.index_pointer,
+            stack: self.stack.clone(),
+
+            memory_window: self.format_memory(from, to, 8),
+        }
     }
 }
@@ -1,37 +1,233 @@
+mod settings;
+
+use std::path::{Path, PathBuf};
 use std::time::{Instant, Duration};
 use std::thread;
 
-use chip_8_emu::sound::RodioSound;
-use chip_8_emu::{ChipEmulator, ChipEmulatorConfig, display::SdlDisplay, keypad::SdlKeypad};
+use chip_8_emu::event::ChipEvent;
+use chip_8_emu::replay::InputLog;
+use chip_8_emu::rewind::RewindBuffer;
+use chip_8_emu::sound::{ChipSound, RodioSound};
+use chip_8_emu::{ChipEmulator, ChipEmulatorConfig, CompatibilityProfile, display::{ChipDisplay, SdlDisplay, SCREEN_HEIGHT, SCREEN_WIDTH}, keypad::{ChipKeypad, SdlController, SdlKeypad}};
+use clap::{Parser, ValueEnum};
 use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use settings::Settings;
 
 const MAX_FRAME_RATE: f64 = 60.;
 
+/// Frames of instructions run at most by a single frame, the rest of a stall is dropped
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+
+/// Speed multiplier range of the - and + keys and multiplier of the turbo key
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 4.;
+const TURBO_SPEED: f64 = 4.;
+
+/// A state is kept every REWIND_INTERVAL frames, up to 60 seconds of emulation
+const REWIND_INTERVAL: u32 = 6;
+const REWIND_CAPACITY: usize = 600;
+
+/// Settings file read from the working directory if no other file is given
+const DEFAULT_SETTINGS_FILE: &str = "chip8rs.toml";
+
+/// Default frontend settings, used if neither the command line
+/// nor the settings file set them
+const DEFAULT_IPS: u32 = 700;
+const DEFAULT_SCALE: u32 = 10;
+const DEFAULT_FG_COLOR: [u8; 4] = [0x00, 0xFF, 0xFF, 0xFF];
+const DEFAULT_BG_COLOR: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+const DEFAULT_FREQUENCY: f32 = 698.;
+const DEFAULT_VOLUME: f32 = 0.3;
+
+/// Chip-8 emulator
+/// The options override the settings file
+#[derive(Parser)]
+#[command(name = "chip8rs", version)]
+struct Args {
+    /// Path of the rom to run
+    rom: PathBuf,
+
+    /// TOML settings file, chip8rs.toml in the working directory by default
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Number of instructions run per second [default: 700]
+    #[arg(long)]
+    ips: Option<u32>,
+
+    /// Initial window scale, every chip-8 pixel is scale x scale window pixels [default: 10]
+    #[arg(long)]
+    scale: Option<u32>,
+
+    /// Color of the pixels on, as a RRGGBB hexadecimal value [default: FFFF00]
+    #[arg(long, value_parser = parse_color)]
+    fg_color: Option<[u8; 4]>,
+
+    /// Color of the pixels off, as a RRGGBB hexadecimal value [default: 000000]
+    #[arg(long, value_parser = parse_color)]
+    bg_color: Option<[u8; 4]>,
+
+    /// Compatibility profile of the emulated interpreter
+    #[arg(long)]
+    quirks: Option<Quirks>,
+
+    /// Disable the sound
+    #[arg(long)]
+    mute: bool,
+
+    /// Seed of the random number generator, random if not set
+    /// A replay use the seed of its recording
+    #[arg(long, conflicts_with = "replay")]
+    seed: Option<u64>,
+
+    /// Record the key presses to the given file, replayed with --replay
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Replay the key presses recorded with --record in the given file
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+}
+
+/// Compatibility profiles selectable from the command line
+#[derive(Clone, Copy, ValueEnum)]
+enum Quirks {
+    Cosmac,
+    Chip48,
+    Schip,
+    Xochip,
+}
+
+// Convert the command line quirks to the emulator profile
+impl From<Quirks> for CompatibilityProfile {
+    fn from(quirks: Quirks) -> Self {
+        match quirks {
+            Quirks::Cosmac => CompatibilityProfile::Vip,
+            Quirks::Chip48 => CompatibilityProfile::Chip48,
+            Quirks::Schip => CompatibilityProfile::Schip,
+            Quirks::Xochip => CompatibilityProfile::XoChip,
+        }
+    }
+}
+
+/// Parse a RRGGBB hexadecimal color into the BGRA format used by the display
+fn parse_color(color: &str) -> Result<[u8; 4], String> {
+    let color = color.trim_start_matches('#');
+    let value = u32::from_str_radix(color, 16)
+        .ok()
+        .filter(|_| color.len() == 6)
+        .ok_or_else(|| format!("invalid color {}, expected RRGGBB", color))?;
+
+    let [_, red, green, blue] = value.to_be_bytes();
+    Ok([blue, green, red, 0xFF])
+}
+
 fn main() {
+    let args = Args::parse();
+
+    // Read the settings file, a missing default file is not an error
+    let settings = match &args.config {
+        Some(path) => Settings::load(path),
+        None if Path::new(DEFAULT_SETTINGS_FILE).exists() => {
+            Settings::load(Path::new(DEFAULT_SETTINGS_FILE))
+        }
+        None => Ok(Settings::default()),
+    };
+    let settings = settings.unwrap_or_else(|error| {
+        eprintln!("Couldn't load the settings: {}", error);
+        std::process::exit(1);
+    });
+
     // Initialize sdl contex and even pump
     let sdl_context = sdl2::init().expect("Couldn't initialize sdl2");
     let mut event_pump = sdl_context.event_pump().expect("Couldn't initialize event pump");
 
     // Initialize display and keypad
-    let mut display =  SdlDisplay::new(&sdl_context, [0x00, 0xFF, 0xFF, 0xFF], [0, 0, 0, 0]).expect("Couldn't create display");
-    let mut keypad = SdlKeypad::default();
+    let window_size = args
+        .scale
+        .map(|scale| (SCREEN_WIDTH * scale.max(1), SCREEN_HEIGHT * scale.max(1)))
+        .or(settings.window_size)
+        .unwrap_or((SCREEN_WIDTH * DEFAULT_SCALE, SCREEN_HEIGHT * DEFAULT_SCALE));
+    let fg_color = args.fg_color.or(settings.fg_color).unwrap_or(DEFAULT_FG_COLOR);
+    let bg_color = args.bg_color.or(settings.bg_color).unwrap_or(DEFAULT_BG_COLOR);
+
+    let mut display = SdlDisplay::with_window_size(&sdl_context, window_size, fg_color, bg_color)
+        .expect("Couldn't create display");
+    let mut keypad = settings.keymap.clone().map(SdlKeypad::with_keymap).unwrap_or_default();
+
+    // Use a game controller if the subsystem is available
+    let mut controller = SdlController::new(&sdl_context).ok();
 
     // Initialize sound system
-    let sound = RodioSound::new(698., 0.3);
+    let volume = if args.mute { 0. } else { settings.volume.unwrap_or(DEFAULT_VOLUME) };
+    let sound = RodioSound::new(settings.frequency.unwrap_or(DEFAULT_FREQUENCY), volume);
 
     // Initialize the emulator
-    let config = ChipEmulatorConfig {
-        instruction_per_second: 700,
-        ..Default::default()
+    let mut config = match args.quirks.or(settings.quirks) {
+        Some(quirks) => ChipEmulatorConfig::from_profile(quirks.into()),
+        None => ChipEmulatorConfig::default(),
+    };
+    settings.apply_quirks(&mut config);
+    config.instruction_per_second = args.ips.or(settings.ips).unwrap_or(DEFAULT_IPS);
+    config.crash_report = true;
+
+    // Read the replayed inputs, the seed of the recording is used
+    // to reproduce the recorded execution
+    let replay = args.replay.as_ref().map(|path| {
+        InputLog::load(path).unwrap_or_else(|error| {
+            eprintln!("Couldn't load the replay {}: {}", path.display(), error);
+            std::process::exit(1);
+        })
+    });
+
+    config.rng_seed = match &replay {
+        Some(log) => log.rng_seed,
+        // A recording need a known seed to be replayed
+        None if args.record.is_some() => Some(args.seed.unwrap_or_else(rand::random)),
+        None => args.seed,
     };
 
-    let mut emulator = ChipEmulator::initialize(config);
-    emulator.load_rom("./rom/RPS.ch8").expect("ROM loading error");
-    //emulator.load_rom("./rom/octojam1title.ch8").expect("ROM loading error");
-    //emulator.load_rom("./rom/glitchGhost.ch8").expect("ROM loading error");
-    //emulator.load_rom("./rom/1dcell.ch8").expect("ROM loading error");
-    //emulator.load_rom("./rom/snake.ch8").expect("ROM loading error");
-    //emulator.load_rom("./rom/test_audio.ch8").expect("ROM loading error");
+    // The recorded and replayed runs execute a fixed number of
+    // instruction per frame to be reproducible
+    let deterministic = args.record.is_some() || replay.is_some();
+    let frame_instructions = (config.instruction_per_second as f64 / MAX_FRAME_RATE).round() as u32;
+    config.max_instructions_per_frame = Some(frame_instructions.max(1) * MAX_CATCH_UP_FRAMES);
+
+    let mut emulator = ChipEmulator::initialize(config).unwrap_or_else(|error| {
+        eprintln!("Invalid emulator configuration: {}", error);
+        std::process::exit(1);
+    });
+
+    // Suggest the right compatibility profile for extension roms
+    emulator.set_event_hook(Box::new(|event| {
+        if let ChipEvent::ExtensionOpcode { address, opcode, profile } = event {
+            eprintln!(
+                "Warning: opcode 0x{:04X} at 0x{:03X} require the {:?} compatibility profile",
+                opcode, address, profile
+            );
+        }
+    }));
+
+    if let Err(error) = emulator.load_rom(&args.rom.to_string_lossy()) {
+        eprintln!("Couldn't load {}: {}", args.rom.display(), error);
+        std::process::exit(1);
+    }
+
+    if let Some(log) = replay {
+        emulator.load_replay(log);
+    }
+    if let Some(path) = &args.record {
+        emulator.start_recording_to(path);
+    }
+
+    // Speed set with the - and + keys, multiplied while the turbo key is held
+    let mut speed = 1.;
+    let mut turbo = false;
+
+    // States restored one per frame while the rewind key is held
+    let mut rewind = RewindBuffer::new(REWIND_CAPACITY, REWIND_INTERVAL);
+    let mut rewinding = false;
 
     // Run emulator loop
     let mut timer = Instant::now();
@@ -44,23 +240,67 @@ fn main() {
             thread::sleep(Duration::from_secs_f64(1. / MAX_FRAME_RATE) - last_frame_time);
         }
         
-        // Update bell status
-        sound.update_bell(emulator.get_bell_status());
-
-        // Update the emulator pressed key
-        emulator.update_key(keypad.get_key());
+        // Update the audio pattern and bell status, the bell is silenced while paused
+        if !emulator.is_paused() {
+            sound.update_pattern(emulator.get_audio_pattern(), emulator.get_audio_sample_rate());
+            sound.update_sound_timer(emulator.get_sound_timer(), emulator.take_sound_timer_reload());
+        }
 
-        // If the emulator video buffer was updated update the screen
-        let (video_buffer, buffer_updated) = emulator.get_video_buffer();
-        if buffer_updated {
-            display.update(video_buffer);
+        // Update the emulator pressed keys
+        let mut keypads: Vec<&dyn ChipKeypad> = vec![&keypad];
+        if let Some(controller) = controller.as_ref() {
+            keypads.push(controller);
+        }
+        if !emulator.is_replaying() {
+            update_keys(&mut emulator, &keypads);
         }
 
         // Handle events
         for event in event_pump.poll_iter() {
-            if !keypad.process_sdl_event(&event) {
+            let controller_event = controller.as_mut().is_some_and(|c| c.process_sdl_event(&event));
+            if !controller_event && !keypad.process_sdl_event(&event) {
                 match event {
                     Event::Quit { .. } => { break 'running; }
+                    // Restart the rom, the hotkeys changing the emulation timing
+                    // are ignored while recording or replaying
+                    Event::KeyDown { keycode: Some(Keycode::F5), repeat: false, .. } if !deterministic => {
+                        emulator.reset_keep_rom();
+                        rewind.clear();
+                    }
+                    // Pause or resume the emulation
+                    Event::KeyDown { keycode: Some(Keycode::P), repeat: false, .. } => {
+                        let paused = !emulator.is_paused();
+                        emulator.set_paused(paused);
+                        display.set_paused_overlay(paused);
+
+                        if paused {
+                            sound.pause();
+                        } else {
+                            sound.resume();
+                        }
+                    }
+                    // Hold backspace to rewind
+                    Event::KeyDown { keycode: Some(Keycode::Backspace), .. } if !deterministic => {
+                        rewinding = true;
+                    }
+                    Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => rewinding = false,
+                    // Hold tab to fast-forward
+                    Event::KeyDown { keycode: Some(Keycode::Tab), .. } if !deterministic => turbo = true,
+                    Event::KeyUp { keycode: Some(Keycode::Tab), .. } => turbo = false,
+                    // Halve or double the speed
+                    Event::KeyDown { keycode: Some(Keycode::Minus | Keycode::KpMinus), .. } if !deterministic => {
+                        speed = f64::max(speed / 2., MIN_SPEED);
+                    }
+                    Event::KeyDown { keycode: Some(Keycode::Equals | Keycode::KpPlus), .. } if !deterministic => {
+                        speed = f64::min(speed * 2., MAX_SPEED);
+                    }
+                    // Run a rom file dropped on the window
+                    Event::DropFile { filename, .. } if !deterministic => {
+                        if let Err(error) = emulator.swap_rom(&filename) {
+                            eprintln!("Couldn't load {}: {}", filename, error);
+                        }
+                        rewind.clear();
+                    }
                     Event::Window { 
                         win_event: WindowEvent::Resized(x, y), .. 
                     } => {
@@ -72,12 +312,63 @@ fn main() {
             }
         }
 
+        // Present the display once if the window was resized during the frame
+        display.present_pending();
+
+        emulator.set_speed_multiplier(if turbo { speed * TURBO_SPEED } else { speed });
+
+        // Step back to the previous state instead of running the frame
+        if rewinding && !emulator.is_paused() {
+            if rewind.rewind(&mut emulator) {
+                display.update(emulator.get_video_buffer().0);
+            }
+
+            continue;
+        }
+
         // Run all the instruction for the frame as quickly as possible
         let cpu_time = timer.elapsed();
 
         let instructions = cpu_time.as_nanos() / emulator.get_cycle_duration().as_nanos();
-        for _ in 0..=instructions {
-            emulator.step();
+        let instructions = u32::try_from(instructions).unwrap_or(u32::MAX).saturating_add(1);
+        let instructions = if deterministic { frame_instructions } else { instructions };
+
+        match emulator.run_frame(instructions) {
+            // If the emulator video buffer was updated update the screen,
+            // fading pixels require an update every frame
+            Ok(frame) => {
+                if frame.buffer_updated || display.is_fading() {
+                    display.update(frame.video_buffer);
+                }
+
+                // Stop once the program exited
+                if emulator.is_halted() {
+                    break 'running;
+                }
+
+                if !emulator.is_paused() {
+                    rewind.record(&emulator);
+                }
+            }
+            Err(error) => {
+                eprintln!("Emulation error: {}", error);
+                if let Some(report) = emulator.crash_report() {
+                    eprintln!("{}", report);
+                }
+
+                break 'running;
+            }
         }
     }
+
+    // Flush the recording and the flag registers before exiting
+    if let Err(error) = emulator.shutdown() {
+        eprintln!("Couldn't save the emulator state: {}", error);
+    }
+}
+
+/// Set the emulator keys pressed on any of the given keypads
+fn update_keys(emulator: &mut ChipEmulator, keypads: &[&dyn ChipKeypad]) {
+    let mask = keypads.iter().fold(0, |mask, keypad| mask | keypad.get_key_bitmask());
+    emulator.set_key_bitmask(mask);
 }
@@ -0,0 +1,125 @@
+use crate::CompatibilityProfile;
+
+/// Description of an opcode implemented by the interpreter
+#[derive(Clone, Copy, Debug)]
+pub struct OpcodeInfo {
+    /// Assembly mnemonic of the instruction
+    pub mnemonic: &'static str,
+    /// Opcode pattern, hexadecimal digits are fixed while
+    /// X, Y, N, NN and NNN are the instruction parameters
+    pub pattern: &'static str,
+    /// Short description of the instruction effect
+    pub description: &'static str,
+    /// First compatibility profile that enable the instruction
+    pub profile: CompatibilityProfile,
+
+    /// Bits of the opcode that are fixed by the pattern
+    pub mask: u16,
+    /// Value of the fixed bits of the opcode
+    pub value: u16,
+}
+
+impl OpcodeInfo {
+    /// Return true if the given opcode match the instruction pattern
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+/// Shorthand used to build the opcode table
+const fn opcode(
+    mnemonic: &'static str,
+    pattern: &'static str,
+    description: &'static str,
+    profile: CompatibilityProfile,
+    mask: u16,
+    value: u16,
+) -> OpcodeInfo {
+    OpcodeInfo { mnemonic, pattern, description, profile, mask, value }
+}
+
+use CompatibilityProfile::{Schip, Vip, XoChip};
+
+/// Every opcode implemented by the interpreter
+const SUPPORTED_OPCODES: &[OpcodeInfo] = &[
+    opcode("CLS", "00E0", "Clear the screen", Vip, 0xFFFF, 0x00E0),
+    opcode("RET", "00EE", "Return from a subroutine", Vip, 0xFFFF, 0x00EE),
+    opcode("SCD nibble", "00CN", "Scroll the display down N pixels", Schip, 0xFFF0, 0x00C0),
+    opcode("SCU nibble", "00DN", "Scroll the selected planes up N pixels", XoChip, 0xFFF0, 0x00D0),
+    opcode("SCR", "00FB", "Scroll the display right 4 pixels", Schip, 0xFFFF, 0x00FB),
+    opcode("SCL", "00FC", "Scroll the display left 4 pixels", Schip, 0xFFFF, 0x00FC),
+    opcode("EXIT", "00FD", "Exit the interpreter", Schip, 0xFFFF, 0x00FD),
+    opcode("LOW", "00FE", "Switch to the 64x32 low resolution mode", Schip, 0xFFFF, 0x00FE),
+    opcode("HIGH", "00FF", "Switch to the 128x64 high resolution mode", Schip, 0xFFFF, 0x00FF),
+    opcode("JP addr", "1NNN", "Jump to address NNN", Vip, 0xF000, 0x1000),
+    opcode("CALL addr", "2NNN", "Call the subroutine at address NNN", Vip, 0xF000, 0x2000),
+    opcode("SE Vx, byte", "3XNN", "Skip the next instruction if VX == NN", Vip, 0xF000, 0x3000),
+    opcode("SNE Vx, byte", "4XNN", "Skip the next instruction if VX != NN", Vip, 0xF000, 0x4000),
+    opcode("SE Vx, Vy", "5XY0", "Skip the next instruction if VX == VY", Vip, 0xF00F, 0x5000),
+    opcode("SAVE Vx - Vy", "5XY2", "Store VX to VY in memory starting at I", XoChip, 0xF00F, 0x5002),
+    opcode("LOAD Vx - Vy", "5XY3", "Load VX to VY from memory starting at I", XoChip, 0xF00F, 0x5003),
+    opcode("LD Vx, byte", "6XNN", "Set VX to NN", Vip, 0xF000, 0x6000),
+    opcode("ADD Vx, byte", "7XNN", "Add NN to VX without carry", Vip, 0xF000, 0x7000),
+    opcode("LD Vx, Vy", "8XY0", "Set VX to VY", Vip, 0xF00F, 0x8000),
+    opcode("OR Vx, Vy", "8XY1", "Set VX to VX | VY", Vip, 0xF00F, 0x8001),
+    opcode("AND Vx, Vy", "8XY2", "Set VX to VX & VY", Vip, 0xF00F, 0x8002),
+    opcode("XOR Vx, Vy", "8XY3", "Set VX to VX ^ VY", Vip, 0xF00F, 0x8003),
+    opcode("ADD Vx, Vy", "8XY4", "Add VY to VX, VF is set to the carry", Vip, 0xF00F, 0x8004),
+    opcode("SUB Vx, Vy", "8XY5", "Set VX to VX - VY, VF is set to not borrow", Vip, 0xF00F, 0x8005),
+    opcode("SHR Vx, Vy", "8XY6", "Shift VX right, VF is set to the shifted out bit", Vip, 0xF00F, 0x8006),
+    opcode("SUBN Vx, Vy", "8XY7", "Set VX to VY - VX, VF is set to not borrow", Vip, 0xF00F, 0x8007),
+    opcode("SHL Vx, Vy", "8XYE", "Shift VX left, VF is set to the shifted out bit", Vip, 0xF00F, 0x800E),
+    opcode("SNE Vx, Vy", "9XY0", "Skip the next instruction if VX != VY", Vip, 0xF00F, 0x9000),
+    opcode("LD I, addr", "ANNN", "Set I to NNN", Vip, 0xF000, 0xA000),
+    opcode("JP V0, addr", "BNNN", "Jump to NNN plus the offset register", Vip, 0xF000, 0xB000),
+    opcode("RND Vx, byte", "CXNN", "Set VX to a random number masked with NN", Vip, 0xF000, 0xC000),
+    opcode("DRW Vx, Vy, 0", "DXY0", "Draw a 16x16 sprite at VX, VY in high resolution", Schip, 0xF00F, 0xD000),
+    opcode("DRW Vx, Vy, nibble", "DXYN", "Draw an N rows sprite at VX, VY", Vip, 0xF000, 0xD000),
+    opcode("SKP Vx", "EX9E", "Skip the next instruction if the key in VX is pressed", Vip, 0xF0FF, 0xE09E),
+    opcode("SKNP Vx", "EXA1", "Skip the next instruction if the key in VX is not pressed", Vip, 0xF0FF, 0xE0A1),
+    opcode("LD Vx, DT", "FX07", "Set VX to the delay timer", Vip, 0xF0FF, 0xF007),
+    opcode("LD Vx, K", "FX0A", "Wait for a key press and store it in VX", Vip, 0xF0FF, 0xF00A),
+    opcode("LD DT, Vx", "FX15", "Set the delay timer to VX", Vip, 0xF0FF, 0xF015),
+    opcode("LD ST, Vx", "FX18", "Set the sound timer to VX", Vip, 0xF0FF, 0xF018),
+    opcode("ADD I, Vx", "FX1E", "Add VX to I", Vip, 0xF0FF, 0xF01E),
+    opcode("LD F, Vx", "FX29", "Set I to the font character in VX", Vip, 0xF0FF, 0xF029),
+    opcode("LD HF, Vx", "FX30", "Set I to the big font character in VX", Schip, 0xF0FF, 0xF030),
+    opcode("LD B, Vx", "FX33", "Store the decimal digits of VX at I", Vip, 0xF0FF, 0xF033),
+    opcode("LD [I], Vx", "FX55", "Store V0 to VX in memory starting at I", Vip, 0xF0FF, 0xF055),
+    opcode("LD Vx, [I]", "FX65", "Load V0 to VX from memory starting at I", Vip, 0xF0FF, 0xF065),
+    opcode("LD R, Vx", "FX75", "Store V0 to VX in the flag registers", Schip, 0xF0FF, 0xF075),
+    opcode("LD Vx, R", "FX85", "Load V0 to VX from the flag registers", Schip, 0xF0FF, 0xF085),
+    opcode("LD I, long", "F000", "Set I to the 16 bit address in the next word", XoChip, 0xFFFF, 0xF000),
+    opcode("PLANE plane", "FN01", "Select the drawing planes N", XoChip, 0xF0FF, 0xF001),
+    opcode("AUDIO", "F002", "Load the 16 bytes audio pattern at I", XoChip, 0xFFFF, 0xF002),
+    opcode("PITCH Vx", "FX3A", "Set the audio pitch register to VX", XoChip, 0xF0FF, 0xF03A),
+];
+
+/// Return the list of opcodes implemented by the interpreter
+pub fn supported_opcodes() -> &'static [OpcodeInfo] {
+    SUPPORTED_OPCODES
+}
+
+/// Return the description of the supported opcode matching the given opcode
+pub fn lookup(opcode: u16) -> Option<&'static OpcodeInfo> {
+    SUPPORTED_OPCODES.iter().find(|info| info.matches(opcode))
+}
+
+/// Return the first compatibility profile defining the given extension opcode,
+/// None for the original Chip-8 opcodes and the unknown ones
+/// Cover the SUPER-CHIP and XO-CHIP opcodes even if not supported by the interpreter
+pub fn extension_profile(opcode: u16) -> Option<CompatibilityProfile> {
+    match opcode {
+        // Scroll, exit, resolution switch, big font and flag registers
+        0x00C0..=0x00CF | 0x00FB..=0x00FF => Some(Schip),
+        _ if opcode & 0xF0FF == 0xF030 => Some(Schip),
+        _ if opcode & 0xF0FF == 0xF075 || opcode & 0xF0FF == 0xF085 => Some(Schip),
+
+        // Scroll up, long index load, plane select and audio
+        0x00D0..=0x00DF | 0xF000 | 0xF002 => Some(XoChip),
+        _ if opcode & 0xF0FF == 0xF001 || opcode & 0xF0FF == 0xF03A => Some(XoChip),
+        _ if opcode & 0xF00F == 0x5002 || opcode & 0xF00F == 0x5003 => Some(XoChip),
+
+        _ => None,
+    }
+}
@@ -0,0 +1,29 @@
+use std::ops::Range;
+
+/// Memory mapped peripheral, the accesses to its address range call
+/// the read and write handlers instead of accessing the program memory
+pub struct Peripheral {
+    /// Address range handled by the peripheral
+    pub(crate) range: Range<u16>,
+
+    /// Called with the address on every read, return the value read
+    pub(crate) read: Box<dyn FnMut(u16) -> u8>,
+    /// Called with the address and value on every write
+    pub(crate) write: Box<dyn FnMut(u16, u8)>,
+}
+
+impl Peripheral {
+    /// Create a peripheral handling the given address range
+    pub fn new(
+        range: Range<u16>,
+        read: Box<dyn FnMut(u16) -> u8>,
+        write: Box<dyn FnMut(u16, u8)>,
+    ) -> Self {
+        Self { range, read, write }
+    }
+
+    /// Return the address range handled by the peripheral
+    pub fn range(&self) -> Range<u16> {
+        self.range.clone()
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Key state change recorded at a given cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputEvent {
+    /// Number of instructions executed since the emulator was initialized
+    pub cycle: u64,
+    /// Pressed keys bitmask, bit 0 correspond to key 0x0 and bit 15 to key 0xF
+    pub keys: u16,
+}
+
+/// Key state changes recorded by ChipEmulator::start_recording,
+/// ordered by cycle
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputLog {
+    /// Seed of the recording emulator, the replay reseed the random
+    /// generator with it, None if the generator was seeded from entropy
+    pub rng_seed: Option<u64>,
+    pub events: Vec<InputEvent>,
+}
+
+impl InputLog {
+    /// Write the log to the given file, the optional `seed N` line
+    /// is followed by one `cycle keys` line per event, with the keys
+    /// bitmask in hexadecimal
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+
+        if let Some(seed) = self.rng_seed {
+            let _ = writeln!(text, "seed {}", seed);
+        }
+        for event in &self.events {
+            let _ = writeln!(text, "{} {:04X}", event.cycle, event.keys);
+        }
+
+        fs::write(path, text)
+    }
+
+    /// Read a log written with save, `#` start a comment
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut log = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let code = line.split('#').next().unwrap_or_default();
+            let invalid_line = || {
+                io::Error::new(io::ErrorKind::InvalidData, format!("invalid input log line {}", i + 1))
+            };
+
+            match code.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => {}
+                ["seed", seed] => log.rng_seed = Some(seed.parse().map_err(|_| invalid_line())?),
+                [cycle, keys] => {
+                    let cycle = cycle.parse().map_err(|_| invalid_line())?;
+                    let keys = u16::from_str_radix(keys, 16).map_err(|_| invalid_line())?;
+
+                    // The replay expect the events ordered by cycle
+                    if log.events.last().is_some_and(|event| event.cycle > cycle) {
+                        return Err(invalid_line());
+                    }
+                    log.events.push(InputEvent { cycle, keys });
+                }
+                _ => return Err(invalid_line()),
+            }
+        }
+
+        Ok(log)
+    }
+}
+
+/// Replay of an input log in progress
+pub(crate) struct Replay {
+    log: InputLog,
+    /// Index of the next event to apply
+    next: usize,
+}
+
+impl Replay {
+    pub(crate) fn new(log: InputLog) -> Self {
+        Self { log, next: 0 }
+    }
+
+    /// Return the key state of the last event due at the given cycle, if any
+    pub(crate) fn keys_at(&mut self, cycle: u64) -> Option<u16> {
+        let mut keys = None;
+
+        while let Some(event) = self.log.events.get(self.next) {
+            if event.cycle > cycle {
+                break;
+            }
+
+            keys = Some(event.keys);
+            self.next += 1;
+        }
+
+        keys
+    }
+
+    /// Return true once every event was applied
+    pub(crate) fn is_finished(&self) -> bool {
+        self.next >= self.log.events.len()
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use crate::{ChipEmulator, ChipState};
+
+/// Bounded history of machine states used to step the emulation back in time
+///
+/// A state is captured every interval frames in the compact binary format
+/// of ChipState::to_bytes, the oldest state is dropped once the capacity
+/// is reached
+pub struct RewindBuffer {
+    states: VecDeque<Vec<u8>>,
+    capacity: usize,
+
+    /// Number of frames between two captured states
+    interval: u32,
+    /// Frames recorded since the last captured state
+    frames: u32,
+}
+
+impl RewindBuffer {
+    /// Create an empty buffer holding up to capacity states,
+    /// captured once every interval frames
+    pub fn new(capacity: usize, interval: u32) -> Self {
+        Self {
+            states: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+
+            interval: interval.max(1),
+            frames: 0,
+        }
+    }
+
+    /// Count a frame, capturing the emulator state if the interval elapsed
+    pub fn record(&mut self, emulator: &ChipEmulator) {
+        self.frames += 1;
+        if self.frames < self.interval {
+            return;
+        }
+        self.frames = 0;
+
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(emulator.snapshot().to_bytes());
+    }
+
+    /// Restore the most recent captured state and remove it from the buffer,
+    /// return false if the buffer is empty or the state couldn't be restored
+    pub fn rewind(&mut self, emulator: &mut ChipEmulator) -> bool {
+        // The frames counted since the restored state are discarded
+        self.frames = 0;
+
+        match self.states.pop_back().map(|bytes| ChipState::from_bytes(&bytes)) {
+            Some(Ok(state)) => emulator.restore(&state).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Remove every captured state, to call when a new rom is loaded
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.frames = 0;
+    }
+
+    /// Number of captured states
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Return true if no state was captured
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::ChipEmulator;
+
+// Implement screenshot methods
+impl ChipEmulator {
+    /// Save the current frame as a PNG image at the native resolution,
+    /// white pixels on a black background
+    pub fn save_screenshot(&self, path: &Path) -> io::Result<()> {
+        self.save_screenshot_with(path, 1, [0xFF, 0xFF, 0xFF], [0x00, 0x00, 0x00])
+    }
+
+    /// Save the current frame as a PNG image, every pixel is scaled to a
+    /// scale x scale square of the given on or off RGB color
+    pub fn save_screenshot_with(
+        &self,
+        path: &Path,
+        scale: u32,
+        on_color: [u8; 3],
+        off_color: [u8; 3],
+    ) -> io::Result<()> {
+        let scale = scale.max(1) as usize;
+        let (width, height) = self.get_screen_size();
+        let (width, height) = (width as usize, height as usize);
+        let pixels = self.unpacked_frame();
+
+        // Build the scaled RGB image row by row
+        let mut data = Vec::with_capacity(width * height * scale * scale * 3);
+        for row in pixels.chunks(width) {
+            let mut line = Vec::with_capacity(width * scale * 3);
+            for pixel in row {
+                let color = if *pixel != 0 { on_color } else { off_color };
+                for _ in 0..scale {
+                    line.extend_from_slice(&color);
+                }
+            }
+
+            for _ in 0..scale {
+                data.extend_from_slice(&line);
+            }
+        }
+
+        // Encode the image
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, (width * scale) as u32, (height * scale) as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&data).map_err(io::Error::other)?;
+
+        Ok(())
+    }
+}
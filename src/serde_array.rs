@@ -0,0 +1,47 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serialize a byte array of any length as a sequence
+pub fn serialize<S, const N: usize>(array: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(array.iter())
+}
+
+/// Deserialize a byte array of any length from a sequence,
+/// fail if the sequence length doesn't match the array length
+pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = Vec::<u8>::deserialize(deserializer)?;
+    let len = bytes.len();
+
+    bytes
+        .try_into()
+        .map_err(|_| D::Error::invalid_length(len, &format!("an array of {} bytes", N).as_str()))
+}
+
+/// Serialize and deserialize an optional byte array of any length
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Wrapper using the array serialization for the option content
+    #[derive(Serialize, Deserialize)]
+    struct Array<const N: usize>(#[serde(with = "super")] [u8; N]);
+
+    pub fn serialize<S, const N: usize>(array: &Option<[u8; N]>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        array.map(Array).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<Option<[u8; N]>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<Array<N>>::deserialize(deserializer)?.map(|array| array.0))
+    }
+}
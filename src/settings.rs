@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use chip_8_emu::keypad::{ChipKey, DEFAULT_KEYMAP};
+use chip_8_emu::ChipEmulatorConfig;
+use clap::ValueEnum;
+use sdl2::keyboard::Keycode;
+use toml::{Table, Value};
+
+use crate::{parse_color, Quirks};
+
+/// Frontend settings read from a TOML file, the settings missing
+/// from the file are None
+///
+/// ```toml
+/// ips = 700
+/// window_size = [640, 320]
+///
+/// [colors]
+/// fg = "FFFF00"
+/// bg = "000000"
+///
+/// [audio]
+/// frequency = 698.0
+/// volume = 0.3
+///
+/// [quirks]
+/// profile = "schip"
+/// wrap_sprites = true
+///
+/// # Chip-8 key = SDL key name
+/// [keys]
+/// A = "Z"
+/// ```
+#[derive(Default)]
+pub struct Settings {
+    pub ips: Option<u32>,
+    pub window_size: Option<(u32, u32)>,
+
+    pub fg_color: Option<[u8; 4]>,
+    pub bg_color: Option<[u8; 4]>,
+
+    pub frequency: Option<f32>,
+    pub volume: Option<f32>,
+
+    /// Compatibility profile and the individual settings applied over it
+    pub quirks: Option<Quirks>,
+    pub copy_y_on_shift: Option<bool>,
+    pub offset_jump_vx: Option<bool>,
+    pub align_pc: Option<bool>,
+    pub wrap_sprites: Option<bool>,
+    pub display_wait: Option<bool>,
+    pub clear_on_resolution_change: Option<bool>,
+    pub key_wait_release: Option<bool>,
+
+    /// Default keymap with the keys of the file replaced
+    pub keymap: Option<HashMap<Keycode, ChipKey>>,
+}
+
+impl Settings {
+    /// Read the settings from the given TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let table: Table = text.parse().map_err(|error: toml::de::Error| error.to_string())?;
+
+        let empty = Table::new();
+        let colors = get(&table, "colors", Value::as_table)?.unwrap_or(&empty);
+        let audio = get(&table, "audio", Value::as_table)?.unwrap_or(&empty);
+        let quirks = get(&table, "quirks", Value::as_table)?.unwrap_or(&empty);
+
+        Ok(Self {
+            ips: get(&table, "ips", as_u32)?,
+            window_size: get(&table, "window_size", as_size)?,
+
+            fg_color: get(colors, "fg", as_color)?,
+            bg_color: get(colors, "bg", as_color)?,
+
+            frequency: get(audio, "frequency", as_f32)?,
+            volume: get(audio, "volume", as_f32)?,
+
+            quirks: get(quirks, "profile", as_quirks)?,
+            copy_y_on_shift: get(quirks, "copy_y_on_shift", Value::as_bool)?,
+            offset_jump_vx: get(quirks, "offset_jump_vx", Value::as_bool)?,
+            align_pc: get(quirks, "align_pc", Value::as_bool)?,
+            wrap_sprites: get(quirks, "wrap_sprites", Value::as_bool)?,
+            display_wait: get(quirks, "display_wait", Value::as_bool)?,
+            clear_on_resolution_change: get(quirks, "clear_on_resolution_change", Value::as_bool)?,
+            key_wait_release: get(quirks, "key_wait_release", Value::as_bool)?,
+
+            keymap: get(&table, "keys", Value::as_table)?.map(keymap).transpose()?,
+        })
+    }
+
+    /// Apply the individual compatibility settings of the file to the configuration
+    pub fn apply_quirks(&self, config: &mut ChipEmulatorConfig) {
+        let quirks = [
+            (self.copy_y_on_shift, &mut config.copy_y_on_shift),
+            (self.offset_jump_vx, &mut config.offset_jump_vx),
+            (self.align_pc, &mut config.align_pc),
+            (self.wrap_sprites, &mut config.wrap_sprites),
+            (self.display_wait, &mut config.display_wait),
+            (self.clear_on_resolution_change, &mut config.clear_on_resolution_change),
+            (self.key_wait_release, &mut config.key_wait_release),
+        ];
+
+        for (setting, value) in quirks {
+            if let Some(setting) = setting {
+                *value = setting;
+            }
+        }
+    }
+}
+
+/// Convert the value of the given key if it's present,
+/// return an error if the value has the wrong type
+fn get<'a, T>(
+    table: &'a Table,
+    key: &str,
+    convert: impl Fn(&'a Value) -> Option<T>,
+) -> Result<Option<T>, String> {
+    table
+        .get(key)
+        .map(|value| convert(value).ok_or_else(|| format!("invalid value for {}", key)))
+        .transpose()
+}
+
+fn as_u32(value: &Value) -> Option<u32> {
+    value.as_integer().and_then(|value| u32::try_from(value).ok())
+}
+
+fn as_f32(value: &Value) -> Option<f32> {
+    value.as_float().or_else(|| value.as_integer().map(|value| value as f64)).map(|value| value as f32)
+}
+
+fn as_size(value: &Value) -> Option<(u32, u32)> {
+    match value.as_array()?.as_slice() {
+        [width, height] => Some((as_u32(width)?, as_u32(height)?)),
+        _ => None,
+    }
+}
+
+fn as_color(value: &Value) -> Option<[u8; 4]> {
+    parse_color(value.as_str()?).ok()
+}
+
+fn as_quirks(value: &Value) -> Option<Quirks> {
+    Quirks::from_str(value.as_str()?, true).ok()
+}
+
+/// Build the keymap from the [keys] table, every entry map a chip-8 key
+/// hexadecimal digit to the name of an SDL key
+fn keymap(keys: &Table) -> Result<HashMap<Keycode, ChipKey>, String> {
+    let mut keymap: HashMap<Keycode, ChipKey> = DEFAULT_KEYMAP.into_iter().collect();
+
+    for (chip_key, keycode) in keys {
+        let chip_key = u8::from_str_radix(chip_key, 16)
+            .ok()
+            .and_then(|key| ChipKey::try_from(key).ok())
+            .ok_or_else(|| format!("invalid chip-8 key {}", chip_key))?;
+        let keycode = keycode
+            .as_str()
+            .and_then(Keycode::from_name)
+            .ok_or_else(|| format!("invalid key name for the chip-8 key {:X}", chip_key as u8))?;
+
+        // The chip-8 key is moved to the new keyboard key
+        keymap.retain(|_, key| *key as u8 != chip_key as u8);
+        keymap.insert(keycode, chip_key);
+    }
+
+    Ok(keymap)
+}
@@ -1,5 +1,241 @@
+use std::cell::Cell;
+use std::time::Duration;
+
 use rodio::{OutputStream, Sink, OutputStreamHandle};
-use rodio::source::{SineWave, Source};
+use rodio::source::Source;
+
+/// Sample rate of the generated audio sources
+const SAMPLE_RATE: u32 = 48000;
+
+/// Sound backend playing the chip-8 bell
+pub trait ChipSound {
+    /// Play the bell while active is true, fed by get_bell_status
+    fn update_bell(&self, active: bool);
+
+    /// Play the given XO-CHIP audio pattern at the given rate in bits per second
+    /// instead of the bell tone, fed by get_audio_pattern and get_audio_sample_rate
+    /// Ignored by default
+    fn update_pattern(&self, _pattern: Option<&[u8; 16]>, _rate: f32) {}
+
+    /// Silence the sound while the emulation is paused
+    /// Ignored by default
+    fn pause(&self) {}
+
+    /// Continue the sound paused by pause when the emulation is resumed
+    /// Ignored by default
+    fn resume(&self) {}
+}
+
+/*
+*
+*   Null sound Implementation
+*
+*/
+
+/// Sound system discarding the bell, for headless runs
+#[derive(Default)]
+pub struct NullSound;
+
+// Implement Chip Sound for null sound
+impl ChipSound for NullSound {
+    fn update_bell(&self, _active: bool) {}
+}
+
+/*
+*
+*   Bell tone source
+*
+*/
+
+/// Shape of the bell tone
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+/// Infinite source generating the bell tone with the given waveform
+#[derive(Clone, Debug)]
+pub struct ToneWave {
+    waveform: Waveform,
+    /// Fraction of period advanced every output sample
+    step: f32,
+    /// Current position in the period, between 0 and 1
+    phase: f32,
+}
+
+impl ToneWave {
+    /// Create a tone source given its waveform and frequency in Hz
+    pub fn new(waveform: Waveform, frequency: f32) -> Self {
+        Self {
+            waveform,
+            step: frequency / SAMPLE_RATE as f32,
+            phase: 0.,
+        }
+    }
+}
+
+impl Iterator for ToneWave {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let phase = self.phase;
+        let value = match self.waveform {
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Square => if phase < 0.5 { 1. } else { -1. },
+            Waveform::Triangle => 1. - 4. * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2. * phase - 1.,
+        };
+
+        // Advance and wrap the phase
+        self.phase = (self.phase + self.step).fract();
+
+        Some(value)
+    }
+}
+
+impl Source for ToneWave {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/*
+*
+*   XO-CHIP audio pattern source
+*
+*/
+
+/// Infinite source looping over the 128 bits of an XO-CHIP audio pattern
+#[derive(Clone, Debug)]
+pub struct PatternWave {
+    pattern: [u8; 16],
+    /// Number of pattern bits advanced every output sample
+    step: f32,
+    /// Current position in the pattern in bits
+    position: f32,
+}
+
+impl PatternWave {
+    /// Create a pattern source given the pattern and its playback rate in bits per second
+    pub fn new(pattern: [u8; 16], rate: f32) -> Self {
+        Self {
+            pattern,
+            step: rate / SAMPLE_RATE as f32,
+            position: 0.,
+        }
+    }
+}
+
+impl Iterator for PatternWave {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // Read the current bit of the pattern
+        let bit = self.position as usize;
+        let value = (self.pattern[bit / 8] >> (7 - bit % 8)) & 0b00000001;
+
+        // Advance and wrap the position
+        self.position += self.step;
+        while self.position >= 128. {
+            self.position -= 128.;
+        }
+
+        Some(if value != 0 { 1. } else { -1. })
+    }
+}
+
+impl Source for PatternWave {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/*
+*
+*   One-pole low-pass filter
+*
+*/
+
+/// Source wrapper softening the wrapped source with a one-pole low-pass filter
+#[derive(Clone, Debug)]
+pub struct LowPass<S> {
+    source: S,
+    /// Smoothing factor computed from the cutoff frequency
+    alpha: f32,
+    /// Last output value
+    value: f32,
+}
+
+impl<S: Source<Item = f32>> LowPass<S> {
+    /// Filter the given source with the given cutoff frequency in Hz
+    pub fn new(source: S, cutoff: f32) -> Self {
+        let sample_rate = source.sample_rate() as f32;
+        let alpha = 1. - (-2. * std::f32::consts::PI * cutoff / sample_rate).exp();
+
+        Self {
+            source,
+            alpha,
+            value: 0.,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for LowPass<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let input = self.source.next()?;
+        self.value += self.alpha * (input - self.value);
+
+        Some(self.value)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for LowPass<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
 
 /*
 *
@@ -7,24 +243,54 @@ use rodio::source::{SineWave, Source};
 *
 */
 
+/// Bell playback mode of the rodio sound system
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BellMode {
+    /// The bell play while the sound timer is not zero,
+    /// the status is polled once per frame
+    OnOff,
+    /// When the sound timer is set the tone is scheduled to stop
+    /// after exactly timer * (1/60) seconds
+    CycleExact,
+}
+
 pub struct RodioSound {
     _stream_handle: OutputStreamHandle,
     _stream: OutputStream,
 
     /// Control the sine wave source stream
     sink: Sink,
+
+    /// Waveform, frequency and volume of the bell
+    waveform: Cell<Waveform>,
+    frequency: Cell<f32>,
+    volume: Cell<f32>,
+    /// Cutoff frequency of the low-pass filter, None if disabled
+    low_pass: Option<f32>,
+
+    /// Audio pattern and playback rate currently played, if any
+    pattern: Cell<Option<([u8; 16], f32)>>,
+
+    /// Bell playback mode and last sound timer value received
+    mode: BellMode,
+    last_timer: Cell<u8>,
 }
 
 // Implement constructor and methods for rodio sound
 impl RodioSound {
     /// Create a new sound system given frequency and volume of the bell
     pub fn new(frequency: f32, volume: f32) -> Self {
+        Self::new_with_waveform(frequency, volume, Waveform::Sine)
+    }
+
+    /// Create a new sound system given frequency, volume and waveform of the bell
+    pub fn new_with_waveform(frequency: f32, volume: f32, waveform: Waveform) -> Self {
         // Create the audio handler and sink
         let (_stream, _stream_handle) = OutputStream::try_default().unwrap();
         let sink = Sink::try_new(&_stream_handle).unwrap();
         
-        // Create a sine wave source and give it to a sink
-        let source = SineWave::new(frequency).amplify(volume);
+        // Create the tone source and give it to a sink
+        let source = ToneWave::new(waveform, frequency).amplify(volume);
         sink.append(source);
         sink.pause();
 
@@ -34,15 +300,175 @@ impl RodioSound {
             _stream,
 
             sink,
+
+            waveform: Cell::new(waveform),
+            frequency: Cell::new(frequency),
+            volume: Cell::new(volume),
+            low_pass: None,
+
+            pattern: Cell::new(None),
+
+            mode: BellMode::OnOff,
+            last_timer: Cell::new(0),
+        }
+    }
+
+    /// Set the bell playback mode
+    pub fn set_bell_mode(&mut self, mode: BellMode) {
+        self.mode = mode;
+        self.last_timer.set(0);
+
+        // In on/off mode an infinite source is paused and resumed,
+        // in cycle exact mode a new source is appended for every tone
+        self.sink.clear();
+        if mode == BellMode::OnOff {
+            self.sink.append(self.bell_source());
+        }
+    }
+
+    /// Update the sound system with the current sound timer value and
+    /// whether the program reloaded it, fed by take_sound_timer_reload
+    /// Must be called at every timer tick in cycle exact mode
+    pub fn update_sound_timer(&self, timer: u8, reloaded: bool) {
+        match self.mode {
+            BellMode::OnOff => self.update_bell(timer != 0),
+            BellMode::CycleExact => {
+                // Every reload schedule a new tone of the timer length,
+                // a reload to zero stop the playing tone
+                if reloaded {
+                    self.sink.clear();
+
+                    if timer != 0 {
+                        let duration = Duration::from_secs_f64(timer as f64 / 60.);
+
+                        self.sink.append(self.bell_source().take_duration(duration));
+                        self.sink.play();
+                    }
+                }
+
+                self.last_timer.set(timer);
+            }
+        }
+    }
+
+    /// Enable the low-pass filter with the given cutoff frequency in Hz
+    /// to soften the waveform, or disable it if None is given
+    pub fn set_low_pass(&mut self, cutoff: Option<f32>) {
+        self.low_pass = cutoff;
+
+        if self.mode == BellMode::OnOff {
+            self.replace_source();
+        }
+    }
+
+    /// Set the waveform of the bell, a playing bell switch to it immediately
+    pub fn set_waveform(&self, waveform: Waveform) {
+        self.waveform.set(waveform);
+        self.rebuild_source();
+    }
+
+    /// Set the volume of the bell, 0 mute it
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.set(volume);
+        self.rebuild_source();
+    }
+
+    /// Set the frequency of the bell in Hz
+    pub fn set_frequency(&self, frequency: f32) {
+        self.frequency.set(frequency);
+        self.rebuild_source();
+    }
+
+    /// Rebuild the source after a bell setting changed
+    /// In cycle exact mode the tone playing is rebuilt for its remaining duration
+    fn rebuild_source(&self) {
+        match self.mode {
+            BellMode::OnOff => self.replace_source(),
+            BellMode::CycleExact => {
+                let timer = self.last_timer.get();
+                if timer != 0 && !self.sink.empty() {
+                    let duration = Duration::from_secs_f64(timer as f64 / 60.);
+
+                    self.sink.clear();
+                    self.sink.append(self.bell_source().take_duration(duration));
+                    self.sink.play();
+                }
+            }
+        }
+    }
+
+    /// Create the bell source from the current audio pattern or the bell tone
+    fn bell_source(&self) -> Box<dyn Source<Item = f32> + Send> {
+        let source: Box<dyn Source<Item = f32> + Send> = match self.pattern.get() {
+            Some((pattern, rate)) => {
+                Box::new(PatternWave::new(pattern, rate).amplify(self.volume.get()))
+            }
+            None => {
+                let tone = ToneWave::new(self.waveform.get(), self.frequency.get());
+                Box::new(tone.amplify(self.volume.get()))
+            }
+        };
+
+        match self.low_pass {
+            Some(cutoff) => Box::new(LowPass::new(source, cutoff)),
+            None => source,
+        }
+    }
+
+    /// Replace the infinite source used in on/off mode
+    fn replace_source(&self) {
+        // Clearing the sink pause it so the playing state is restored manually
+        let paused = self.sink.is_paused();
+        self.sink.clear();
+        self.sink.append(self.bell_source());
+
+        if !paused {
+            self.sink.play();
         }
     }
+}
 
+// Implement Chip Sound for rodio sound
+impl ChipSound for RodioSound {
     /// Update the current bell status to the given input
-    pub fn update_bell(&self, bell_status: bool) {
+    fn update_bell(&self, bell_status: bool) {
         if bell_status {
             self.sink.play();
         } else {
             self.sink.pause();
         }
     }
+
+    /// Play the given XO-CHIP audio pattern at the given rate in bits per second
+    /// instead of the bell tone, or go back to the tone if no pattern is given
+    /// The source is rebuilt only when the pattern or rate change
+    fn update_pattern(&self, pattern: Option<&[u8; 16]>, rate: f32) {
+        let pattern = pattern.map(|pattern| (*pattern, rate));
+        if self.pattern.get() == pattern {
+            return;
+        }
+        self.pattern.set(pattern);
+
+        // In cycle exact mode the new source is used from the next tone
+        if self.mode == BellMode::CycleExact {
+            return;
+        }
+
+        self.replace_source();
+    }
+
+    /// Pause the playing bell, in cycle exact mode the scheduled tone
+    /// keep its remaining duration
+    fn pause(&self) {
+        self.sink.pause();
+    }
+
+    /// Resume the bell paused by pause
+    /// In on/off mode the bell status is restored by the next update_bell,
+    /// in cycle exact mode the scheduled tone, if any, continue playing
+    fn resume(&self) {
+        if self.mode == BellMode::CycleExact && !self.sink.empty() {
+            self.sink.play();
+        }
+    }
 }
@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::ChipError;
+use crate::{ChipEmulator, ChipState, MAX_MEMORY_SIZE, MIN_MEMORY_SIZE};
+
+/// Magic number and version at the start of a binary state
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+const STATE_VERSION: u8 = 1;
+
+// Implement the binary encoding of the machine state
+impl ChipState {
+    /// Encode the state in a compact little endian binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.memory.len() + self.video_buffer.len() + 128);
+
+        bytes.extend_from_slice(STATE_MAGIC);
+        bytes.push(STATE_VERSION);
+
+        bytes.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.memory);
+        bytes.extend_from_slice(&self.registers);
+        bytes.extend_from_slice(&self.program_counter.to_le_bytes());
+        bytes.extend_from_slice(&self.index_pointer.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.stack.len() as u32).to_le_bytes());
+        for address in &self.stack {
+            bytes.extend_from_slice(&address.to_le_bytes());
+        }
+
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+
+        match &self.audio_pattern {
+            Some(pattern) => {
+                bytes.push(1);
+                bytes.extend_from_slice(pattern);
+            }
+            None => bytes.push(0),
+        }
+        bytes.push(self.pitch);
+        bytes.extend_from_slice(&self.rpl_flags);
+
+        bytes.extend_from_slice(&self.keys.to_le_bytes());
+        bytes.push(self.wait_key.unwrap_or(0xFF));
+
+        bytes.push(self.hires as u8);
+        bytes.push(self.planes);
+        bytes.extend_from_slice(&(self.video_buffer.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.video_buffer);
+
+        bytes
+    }
+
+    /// Decode a state encoded with to_bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChipError> {
+        let mut reader = StateReader { bytes };
+
+        if reader.take(4)? != STATE_MAGIC || reader.u8()? != STATE_VERSION {
+            return Err(ChipError::InvalidState);
+        }
+
+        // The memory size must be a valid configured size
+        let memory_len = reader.u32()? as usize;
+        if !(MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&memory_len) {
+            return Err(ChipError::InvalidState);
+        }
+        let memory = reader.take(memory_len)?.to_vec();
+        let registers = reader.array()?;
+        let program_counter = reader.u16()?;
+        let index_pointer = reader.u16()?;
+
+        let stack_len = reader.u32()?;
+        let stack = (0..stack_len).map(|_| reader.u16()).collect::<Result<_, _>>()?;
+
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+
+        let audio_pattern = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.array()?),
+        };
+        let pitch = reader.u8()?;
+        let rpl_flags = reader.array()?;
+
+        let keys = reader.u16()?;
+        let wait_key = match reader.u8()? {
+            0xFF => None,
+            key @ 0x0..=0xF => Some(key),
+            _ => return Err(ChipError::InvalidState),
+        };
+
+        let hires = reader.u8()? != 0;
+        let planes = reader.u8()?;
+        let video_len = reader.u32()? as usize;
+        let video_buffer = reader.take(video_len)?.to_vec();
+
+        // Trailing bytes mean the state was not produced by to_bytes
+        if !reader.bytes.is_empty() {
+            return Err(ChipError::InvalidState);
+        }
+
+        Ok(Self {
+            memory,
+            registers,
+            program_counter,
+            index_pointer,
+            stack,
+            delay_timer,
+            sound_timer,
+            audio_pattern,
+            pitch,
+            rpl_flags,
+            keys,
+            wait_key,
+            hires,
+            planes,
+            video_buffer,
+        })
+    }
+}
+
+/// Cursor over the bytes of an encoded state
+struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    /// Consume the given number of bytes
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ChipError> {
+        if self.bytes.len() < len {
+            return Err(ChipError::InvalidState);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ChipError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, ChipError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ChipError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, ChipError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
+
+// Implement save states methods
+impl ChipEmulator {
+    /// Write the machine state to the given file in the binary state format
+    pub fn save_state(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.snapshot().to_bytes())
+    }
+
+    /// Restore the machine state saved with save_state in the given file
+    /// The state is parsed and checked before being applied, an invalid file
+    /// or a state that doesn't fit the configuration leave the emulator untouched
+    pub fn load_state(&mut self, path: &Path) -> io::Result<()> {
+        let bytes = fs::read(path)?;
+        ChipState::from_bytes(&bytes)
+            .and_then(|state| self.restore(&state))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
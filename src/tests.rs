@@ -0,0 +1,1159 @@
+use crate::*;
+
+/// Create an emulator with the given configuration and a fixed seed,
+/// running the given rom
+fn emulator_with(config: ChipEmulatorConfig, rom: &[u8]) -> ChipEmulator {
+    let mut emulator = ChipEmulator::initialize(ChipEmulatorConfig { rng_seed: Some(0), ..config }).unwrap();
+    emulator.load_rom_bytes(rom).unwrap();
+
+    emulator
+}
+
+/// Create an emulator with the default configuration running the given rom
+fn emulator(rom: &[u8]) -> ChipEmulator {
+    emulator_with(ChipEmulatorConfig::default(), rom)
+}
+
+#[test]
+fn collision_flag_reset_every_frame() {
+    let mut emulator = emulator(&[
+        0xA0, 0x50, // LD I, 0x050
+        0xD0, 0x05, // DRW V0, V0, 5
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x12, 0x06, // JP 0x206
+    ]);
+
+    // The first draw doesn't collide
+    emulator.run_frame(2).unwrap();
+    assert!(!emulator.collision_occurred_last_frame());
+
+    // The second draw erase the first one
+    emulator.run_frame(1).unwrap();
+    assert!(emulator.collision_occurred_last_frame());
+
+    // A frame without draws reset the flag
+    emulator.run_frame(3).unwrap();
+    assert!(!emulator.collision_occurred_last_frame());
+}
+
+/// Run the given number of instructions
+fn run(emulator: &mut ChipEmulator, instructions: u32) {
+    for _ in 0..instructions {
+        emulator.step().unwrap();
+    }
+}
+
+/// Reference draw wrapping or clipping the sprite across the edges
+/// one pixel at a time, return the value of VF
+fn reference_draw(screen: &mut [u8], sprite: &[u8], (x, y): (usize, usize), wrap: bool) -> u8 {
+    let mut vf = 0;
+
+    for (row, bits) in sprite.iter().enumerate() {
+        for bit in 0..8 {
+            let (pixel_x, pixel_y) = (x + bit, y + row);
+            if !wrap && (pixel_x >= 64 || pixel_y >= 32) {
+                continue;
+            }
+
+            let pixel = &mut screen[pixel_y % 32 * 64 + pixel_x % 64];
+            let sprite_pixel = bits >> (7 - bit) & 1;
+
+            vf |= *pixel & sprite_pixel;
+            *pixel ^= sprite_pixel;
+        }
+    }
+
+    vf
+}
+
+/// Draw the font zero at the given position, again one pixel further
+/// and again at the position, return the frame and VF after every draw
+fn draw_sequence(packed_video: bool, wrap_sprites: bool, (x, y): (u8, u8)) -> Vec<(Vec<u8>, u8)> {
+    let config = ChipEmulatorConfig { packed_video, wrap_sprites, ..Default::default() };
+    let mut emulator = emulator_with(config, &[
+        0x60, x,     // LD V0, x
+        0x61, y,     // LD V1, y
+        0x62, x + 1, // LD V2, x + 1
+        0xA0, 0x50,  // LD I, 0x050
+        0xD0, 0x15,  // DRW V0, V1, 5
+        0xD2, 0x15,  // DRW V2, V1, 5
+        0xD0, 0x15,  // DRW V0, V1, 5
+    ]);
+    run(&mut emulator, 4);
+
+    (0..3)
+        .map(|_| {
+            run(&mut emulator, 1);
+            (emulator.get_video_buffer().0.to_vec(), emulator.registers[0x0F])
+        })
+        .collect()
+}
+
+#[test]
+fn draw_match_reference_draw() {
+    let zero = &fonts::DEFAULT_FONT[..5];
+
+    // Fully on screen, across the right edge, the bottom edge and the corner
+    for (x, y) in [(10, 5), (59, 5), (10, 30), (62, 30)] {
+        for wrap_sprites in [false, true] {
+            let mut screen = vec![0; 64 * 32];
+            let expected: Vec<(Vec<u8>, u8)> = [x, x + 1, x]
+                .into_iter()
+                .map(|x| {
+                    let vf = reference_draw(&mut screen, zero, (x as usize, y as usize), wrap_sprites);
+                    (screen.clone(), vf)
+                })
+                .collect();
+
+            // The packed buffer always use the general path
+            for packed_video in [false, true] {
+                let draws = draw_sequence(packed_video, wrap_sprites, (x, y));
+                assert_eq!(
+                    draws, expected,
+                    "sprite at {:?}, wrap {}, packed {}", (x, y), wrap_sprites, packed_video
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn draw_xor_and_collision() {
+    let draws = draw_sequence(false, false, (10, 5));
+    let row = |frame: &[u8], y: usize| frame[y * 64 + 10..y * 64 + 16].to_vec();
+
+    // The first draw set the pixels of the zero top row
+    let (frame, vf) = &draws[0];
+    assert_eq!(row(frame, 5), [1, 1, 1, 1, 0, 0]);
+    assert_eq!(*vf, 0);
+
+    // The shifted zero toggle the overlapping pixels off
+    let (frame, vf) = &draws[1];
+    assert_eq!(row(frame, 5), [1, 0, 0, 0, 1, 0]);
+    assert_eq!(*vf, 1);
+
+    // Drawing the first zero again leave only the shifted one
+    let (frame, vf) = &draws[2];
+    assert_eq!(row(frame, 5), [0, 1, 1, 1, 1, 0]);
+    assert_eq!(*vf, 1);
+}
+
+#[test]
+fn draw_clamped_to_memory_end() {
+    let mut emulator = emulator(&[
+        0xAF, 0xFE, // LD I, 0xFFE
+        0xD0, 0x08, // DRW V0, V0, 8
+    ]);
+    emulator.memory[0xFFE..].copy_from_slice(&[0xFF, 0xFF]);
+    run(&mut emulator, 2);
+
+    // Only the two rows left in memory are drawn
+    let frame = emulator.get_video_buffer().0.to_vec();
+    let (drawn, rest) = frame.split_at(64 * 2);
+    assert!(drawn.chunks(64).all(|row| row[..8] == [1; 8] && row[8..].iter().all(|pixel| *pixel == 0)));
+    assert!(rest.iter().all(|pixel| *pixel == 0));
+}
+
+#[test]
+fn pitch_sample_rate() {
+    let mut emulator = emulator_with(ChipEmulatorConfig::from_profile(CompatibilityProfile::XoChip), &[
+        0x60, 0x70, // LD V0, 112
+        0xF0, 0x3A, // PITCH V0
+        0x60, 0x10, // LD V0, 16
+        0xF0, 0x3A, // PITCH V0
+    ]);
+
+    // The default pitch of 64 play the pattern at 4000 Hz
+    assert_eq!(emulator.get_audio_sample_rate(), 4000.);
+
+    // Every 48 pitch steps double or halve the rate
+    run(&mut emulator, 2);
+    assert_eq!(emulator.get_audio_sample_rate(), 8000.);
+
+    run(&mut emulator, 2);
+    assert_eq!(emulator.get_audio_sample_rate(), 2000.);
+}
+
+#[test]
+fn sound_timer_reload_detected() {
+    let mut emulator = emulator(&[
+        0x60, 0x05, // LD V0, 5
+        0xF0, 0x18, // LD ST, V0
+        0x60, 0x03, // LD V0, 3
+        0xF0, 0x18, // LD ST, V0
+    ]);
+    assert!(!emulator.take_sound_timer_reload());
+
+    run(&mut emulator, 2);
+    assert!(emulator.take_sound_timer_reload());
+    assert!(!emulator.take_sound_timer_reload());
+
+    // A reload with a lower value is reported too
+    run(&mut emulator, 2);
+    assert!(emulator.take_sound_timer_reload());
+    assert_eq!(emulator.get_sound_timer(), 3);
+}
+
+#[test]
+fn odd_jump_target() {
+    for (align_pc, expected) in [(false, 0x205), (true, 0x204)] {
+        // Jump and call to an odd address
+        for (name, rom) in [("JP 0x205", [0x12, 0x05]), ("CALL 0x205", [0x22, 0x05])] {
+            let config = ChipEmulatorConfig { align_pc, ..Default::default() };
+            let mut emulator = emulator_with(config, &rom);
+            run(&mut emulator, 1);
+
+            assert_eq!(emulator.program_counter, expected, "{}, align_pc {}", name, align_pc);
+        }
+    }
+}
+
+#[test]
+fn store_load_index_per_profile() {
+    let profiles = [
+        (CompatibilityProfile::Vip, 0x303),
+        (CompatibilityProfile::Chip48, 0x302),
+        (CompatibilityProfile::Schip, 0x300),
+    ];
+
+    for (profile, expected) in profiles {
+        for (name, instruction) in [("LD [I], V2", [0xF2, 0x55]), ("LD V2, [I]", [0xF2, 0x65])] {
+            // LD I, 0x300 followed by the instruction
+            let rom = [0xA3, 0x00, instruction[0], instruction[1]];
+            let mut emulator = emulator_with(ChipEmulatorConfig::from_profile(profile), &rom);
+            run(&mut emulator, 2);
+
+            assert_eq!(emulator.index_pointer, expected, "{}, {:?}", name, profile);
+        }
+    }
+}
+
+#[test]
+fn instructions_per_frame_limit() {
+    // Every ADD V0, 0 instruction move the program counter by 2
+    let rom = [0x70, 0x00].repeat(1500);
+    let config = ChipEmulatorConfig { instruction_per_second: 40_000, ..Default::default() };
+    let limited = ChipEmulatorConfig { max_instructions_per_frame: Some(100), ..config.clone() };
+
+    // No limit by default, 25 ms run 1000 instructions
+    let mut emulator = emulator_with(config.clone(), &rom);
+    emulator.tick(25.).unwrap();
+    assert_eq!(emulator.program_counter, 0x200 + 2 * 1000);
+
+    let mut emulator = emulator_with(config, &rom);
+    emulator.run_frame(1000).unwrap();
+    assert_eq!(emulator.program_counter, 0x200 + 2 * 1000);
+
+    // The limit apply to the single 60 Hz frame of the elapsed time
+    let mut emulator = emulator_with(limited.clone(), &rom);
+    emulator.tick(25.).unwrap();
+    assert_eq!(emulator.program_counter, 0x200 + 2 * 100);
+
+    let mut emulator = emulator_with(limited, &rom);
+    emulator.run_frame(1000).unwrap();
+    assert_eq!(emulator.program_counter, 0x200 + 2 * 100);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn config_toml_round_trip() {
+    let configs = [
+        ChipEmulatorConfig::default(),
+        ChipEmulatorConfig::from_profile(CompatibilityProfile::XoChip),
+        ChipEmulatorConfig {
+            max_instructions_per_frame: Some(500),
+            logic_vf: LogicVfBehavior::ResetToZero,
+            ..ChipEmulatorConfig::from_profile(CompatibilityProfile::Vip)
+        },
+        ChipEmulatorConfig { big_font: None, ..Default::default() },
+    ];
+
+    for config in configs {
+        let document = config.to_toml().unwrap();
+        assert_eq!(ChipEmulatorConfig::from_toml(&document).unwrap(), config, "{}", document);
+    }
+}
+
+#[test]
+fn ambiguous_5xyn_per_instruction_set() {
+    for instruction_set in [InstructionSet::Chip8, InstructionSet::XoChip] {
+        // 5XY1 is not defined by any instruction set
+        let config = ChipEmulatorConfig { instruction_set, ..Default::default() };
+        let mut emulator = emulator_with(config, &[0x51, 0x21]);
+        assert_eq!(emulator.step(), Err(ChipError::UnknownOpcode([0x51, 0x21])));
+
+        // 5XY2 store registers only on XO-CHIP
+        let config = ChipEmulatorConfig { instruction_set, ..Default::default() };
+        let mut emulator = emulator_with(config, &[
+            0x61, 0xAB, // LD V1, 0xAB
+            0x62, 0xCD, // LD V2, 0xCD
+            0xA3, 0x00, // LD I, 0x300
+            0x51, 0x22, // SAVE V1 - V2
+        ]);
+        let result = (0..4).try_for_each(|_| emulator.step());
+
+        if instruction_set == InstructionSet::XoChip {
+            assert_eq!(result, Ok(()));
+            assert_eq!(emulator.memory[0x300..0x302], [0xAB, 0xCD]);
+        } else {
+            assert_eq!(result, Err(ChipError::UnknownOpcode([0x51, 0x22])));
+        }
+    }
+}
+
+#[test]
+fn low_pass_attenuate_high_frequencies() {
+    use sound::{LowPass, PatternWave};
+
+    // Alternating bits play a square wave at half the pattern rate
+    let square = |frequency: f32| PatternWave::new([0xAA; 16], frequency * 2.);
+
+    // Peak to peak amplitude once the filter settled
+    let peak_to_peak = |samples: Vec<f32>| {
+        let max = samples.iter().copied().fold(f32::MIN, f32::max);
+        let min = samples.iter().copied().fold(f32::MAX, f32::min);
+
+        max - min
+    };
+    let filtered = |frequency| {
+        let filter = LowPass::new(square(frequency), 500.);
+        peak_to_peak(filter.skip(4096).take(4096).collect())
+    };
+
+    // A square wave swing between -1 and 1
+    assert_eq!(peak_to_peak(square(4000.).take(4096).collect()), 2.);
+
+    // High frequencies are attenuated, low frequencies mostly pass
+    assert!(filtered(4000.) < 0.5);
+    assert!(filtered(50.) > 1.8);
+}
+
+#[test]
+fn framebuffer_phash_tolerate_single_pixel() {
+    let mut emulator = emulator(&[
+        0xA0, 0x50, // LD I, 0x050
+        0x60, 0x08, // LD V0, 8
+        0xD0, 0x15, // loop: DRW V0, V1, 5
+        0x70, 0x06, // ADD V0, 6
+        0x71, 0x03, // ADD V1, 3
+        0x31, 0x1B, // SE V1, 27
+        0x12, 0x04, // JP loop
+        0x12, 0x0E, // end: JP end
+    ]);
+    run(&mut emulator, 50);
+
+    let frame = emulator.save_display();
+    let (hash, phash) = (emulator.framebuffer_hash(), emulator.framebuffer_phash());
+
+    // The same frame always produce the same hashes
+    emulator.restore_display(&frame).unwrap();
+    assert_eq!(emulator.framebuffer_hash(), hash);
+    assert_eq!(emulator.framebuffer_phash(), phash);
+
+    // A single changed pixel change the exact hash only
+    let mut flicker = frame.clone();
+    flicker[40 * 64 / 2 + 1] ^= 1;
+    emulator.restore_display(&flicker).unwrap();
+    assert_ne!(emulator.framebuffer_hash(), hash);
+    assert!((emulator.framebuffer_phash() ^ phash).count_ones() <= 1);
+
+    // The inverted frame is far from the original one
+    let inverted: Vec<u8> = frame.iter().map(|pixel| pixel ^ 1).collect();
+    emulator.restore_display(&inverted).unwrap();
+    assert!((emulator.framebuffer_phash() ^ phash).count_ones() > 16);
+}
+
+#[test]
+fn offset_jump_per_profile() {
+    use CompatibilityProfile::*;
+
+    for (profile, offset_vx) in [(Vip, false), (Chip48, true), (Schip, true), (XoChip, false)] {
+        let mut emulator = emulator_with(ChipEmulatorConfig::from_profile(profile), &[
+            0x60, 0x10, // LD V0, 0x10
+            0x63, 0x20, // LD V3, 0x20
+            0xB3, 0x00, // JP V0, 0x300
+        ]);
+        run(&mut emulator, 3);
+
+        // BXNN with X = 3 add V3 instead of V0
+        let expected = if offset_vx { 0x320 } else { 0x310 };
+        assert_eq!(emulator.program_counter, expected, "{:?}", profile);
+    }
+}
+
+#[test]
+fn sprite_clamped_event_and_strict_error() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let rom = [
+        0xAF, 0xFA, // LD I, 0xFFA
+        0xD0, 0x0F, // DRW V0, V0, 15
+    ];
+
+    // The lenient mode draw the available rows and notify the clamp
+    let mut emulator = emulator(&rom);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let hook_events = Rc::clone(&events);
+    emulator.set_event_hook(Box::new(move |event| hook_events.borrow_mut().push(event)));
+
+    run(&mut emulator, 2);
+    assert!(events
+        .borrow()
+        .contains(&ChipEvent::SpriteClamped { address: 0xFFA, rows: 15, available: 6 }));
+
+    // The strict mode fail without drawing
+    let config = ChipEmulatorConfig { strict: true, ..Default::default() };
+    let mut emulator = emulator_with(config, &rom);
+    emulator.memory[0xFFA..].copy_from_slice(&[0xFF; 6]);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.step(), Err(ChipError::MemoryOutOfBounds { address: 0x1000 }));
+    assert!(emulator.unpacked_frame().iter().all(|pixel| *pixel == 0));
+}
+
+#[test]
+fn validate_rom_checks() {
+    // A well-formed rom pass and suggest the base profile
+    let rom = [
+        0xA0, 0x50, // start: LD I, 0x050
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x12, 0x00, // JP start
+    ];
+    let info = ChipEmulator::validate_rom(&rom).unwrap();
+    assert_eq!(info.len, 6);
+    assert_eq!(info.suggested_profile, CompatibilityProfile::Vip);
+
+    // A first jump outside of the rom is rejected
+    let rom = [
+        0x14, 0x00, // JP 0x400
+        0x00, 0xE0, // CLS
+    ];
+    assert_eq!(ChipEmulator::validate_rom(&rom), Err(ChipError::JumpOutOfRom { address: 0x400 }));
+
+    // A rom filling the largest memory, its end address overflow 16 bits
+    let mut rom = [0x00, 0xE0].repeat(0x7F00);
+    rom[..2].copy_from_slice(&[0x1F, 0xFE]);
+    assert_eq!(ChipEmulator::validate_rom(&rom).unwrap().len, 0xFE00);
+
+    rom[..2].copy_from_slice(&[0x11, 0xFE]);
+    assert_eq!(ChipEmulator::validate_rom(&rom), Err(ChipError::JumpOutOfRom { address: 0x1FE }));
+
+    // A rom larger than the largest memory is rejected
+    let rom = [0x00, 0xE0].repeat(0x8000);
+    assert_eq!(
+        ChipEmulator::validate_rom(&rom),
+        Err(ChipError::RomTooLarge { size: 0x10000, max: 0xFE00 })
+    );
+}
+
+#[test]
+fn packed_video_buffer_output() {
+    let frames: Vec<_> = [false, true]
+        .into_iter()
+        .map(|packed_video| {
+            let config = ChipEmulatorConfig { packed_video, ..Default::default() };
+            let mut emulator = emulator_with(config, &[
+                0xA0, 0x50, // LD I, 0x050
+                0x60, 0x3D, // LD V0, 61
+                0xD0, 0x15, // DRW V0, V1, 5
+            ]);
+            run(&mut emulator, 3);
+
+            // The packed buffer hold one bit per pixel
+            let packed = emulator.packed_video_buffer().map(<[u8]>::to_vec);
+            assert_eq!(packed.as_ref().map(Vec::len), packed_video.then_some(64 * 32 / 8));
+            assert_eq!(emulator.video_buffer_footprint() < 128 * 64, packed_video);
+
+            // The display always get one byte per pixel
+            let frame = emulator.get_video_buffer().0.to_vec();
+            assert_eq!(frame, emulator.unpacked_frame());
+            frame
+        })
+        .collect();
+
+    assert_eq!(frames[0], frames[1]);
+}
+
+#[test]
+fn load_rom_bytes_reject_large_rom() {
+    let mut emulator = ChipEmulator::initialize(ChipEmulatorConfig::default()).unwrap();
+
+    // The largest rom fill the memory after 0x200
+    emulator.load_rom_bytes(&[0xAB; 0xE00]).unwrap();
+    assert_eq!(emulator.memory[0xFFF], 0xAB);
+    assert_eq!(emulator.program_counter, 0x200);
+
+    assert!(matches!(
+        emulator.load_rom_bytes(&[0; 0xE01]),
+        Err(RomError::TooLarge { size: 0xE01, max: 0xE00 })
+    ));
+}
+
+#[test]
+fn timers_catch_up_elapsed_ticks() {
+    let mut emulator = emulator(&[
+        0x60, 0x0A, // LD V0, 10
+        0xF0, 0x15, // LD DT, V0
+        0xF0, 0x18, // LD ST, V0
+        0x12, 0x06, // loop: JP loop
+    ]);
+    run(&mut emulator, 3);
+
+    // A 50 ms gap contain three 60 Hz ticks
+    emulator.tick(50.).unwrap();
+    assert_eq!(emulator.delay_timer, 7);
+    assert_eq!(emulator.sound_timer, 7);
+
+    // A long stall saturate the timers at zero
+    emulator.tick(1000.).unwrap();
+    assert_eq!(emulator.delay_timer, 0);
+    assert_eq!(emulator.sound_timer, 0);
+}
+
+#[test]
+fn schip_scroll() {
+    let mut emulator = emulator_with(ChipEmulatorConfig::from_profile(CompatibilityProfile::Schip), &[
+        0x00, 0xC2, // SCD 2
+        0x00, 0xFB, // SCR
+        0x00, 0xFC, // SCL
+        0x00, 0xFC, // SCL
+    ]);
+
+    // Light the pixels at (10, 10) and at the top right corner
+    let mut frame = vec![0; 64 * 32];
+    frame[10 * 64 + 10] = 1;
+    frame[63] = 1;
+    emulator.restore_display(&frame).unwrap();
+    emulator.get_video_buffer();
+
+    let lit = |emulator: &ChipEmulator| {
+        let frame = emulator.unpacked_frame();
+        (0..frame.len()).filter(|i| frame[*i] != 0).map(|i| (i % 64, i / 64)).collect::<Vec<_>>()
+    };
+
+    // 00C2 scroll down 2 pixels
+    run(&mut emulator, 1);
+    assert_eq!(lit(&emulator), [(63, 2), (10, 12)]);
+    assert!(emulator.get_video_buffer().1);
+
+    // 00FB scroll right 4 pixels, the corner pixel is discarded
+    run(&mut emulator, 1);
+    assert_eq!(lit(&emulator), [(14, 12)]);
+
+    // 00FC scroll left 4 pixels, the vacated column stay clear
+    run(&mut emulator, 2);
+    assert_eq!(lit(&emulator), [(6, 12)]);
+    assert!(emulator.get_video_buffer().1);
+}
+
+#[test]
+fn seeded_random_sequence() {
+    let sequence = |rng_seed| {
+        let config = ChipEmulatorConfig { rng_seed: Some(rng_seed), ..Default::default() };
+        let mut emulator = ChipEmulator::initialize(config).unwrap();
+        emulator.load_rom_bytes(&[
+            0xC0, 0xFF, // loop: RND V0, 0xFF
+            0x12, 0x00, // JP loop
+        ]).unwrap();
+
+        (0..32)
+            .map(|_| {
+                run(&mut emulator, 2);
+                emulator.registers[0]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // The same seed generate the same values, another seed different ones
+    assert_eq!(sequence(42), sequence(42));
+    assert_ne!(sequence(42), sequence(43));
+}
+
+#[test]
+fn store_three_registers_index() {
+    for (store_load_index, expected) in [(StoreLoadIndex::IncrementXPlusOne, 0x303), (StoreLoadIndex::Unchanged, 0x300)] {
+        let config = ChipEmulatorConfig { store_load_index, ..Default::default() };
+        let mut emulator = emulator_with(config, &[
+            0x60, 0x01, // LD V0, 1
+            0x61, 0x02, // LD V1, 2
+            0x62, 0x03, // LD V2, 3
+            0xA3, 0x00, // LD I, 0x300
+            0xF2, 0x55, // LD [I], V2
+        ]);
+        run(&mut emulator, 5);
+
+        // The registers are stored whatever the quirk, only I differ
+        assert_eq!(emulator.memory[0x300..0x304], [1, 2, 3, 0]);
+        assert_eq!(emulator.index_pointer, expected, "{:?}", store_load_index);
+    }
+}
+
+#[test]
+fn crash_report_disassemble_instruction() {
+    let config = ChipEmulatorConfig { crash_report: true, ..Default::default() };
+    let mut emulator = emulator_with(config, &[
+        0x63, 0x2A, // LD V3, 0x2A
+        0x00, 0xEE, // RET
+    ]);
+
+    // The return with an empty stack fail
+    run(&mut emulator, 1);
+    assert_eq!(emulator.step(), Err(ChipError::StackUnderflow));
+
+    let report = emulator.crash_report().unwrap();
+    assert_eq!(report.address, 0x202);
+    assert_eq!(report.disassembly, "RET");
+    assert_eq!(report.registers[3], 0x2A);
+    assert!(report.to_string().contains("0x202: 00 EE  (RET)"));
+}
+
+#[test]
+fn disassemble_known_opcodes_and_labels() {
+    let rom = [
+        0x6A, 0x02, // LD VA, 0x02
+        0x80, 0x14, // ADD V0, V1
+        0xD0, 0x1F, // DRW V0, V1, 15
+        0x22, 0x0A, // CALL 0x20A
+        0x12, 0x00, // JP 0x200
+        0x00, 0xEE, // RET
+        0x13, 0x00, // JP 0x300, outside of the bytes
+        0xFF, 0xFF, // unknown word
+    ];
+    let lines = disasm::disassemble(&rom, 0x200);
+
+    let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(texts, [
+        "LD VA, 0x02",
+        "ADD V0, V1",
+        "DRW V0, V1, 15",
+        "CALL L20A",
+        "JP L200",
+        "RET",
+        "JP 0x300",
+        "DB 0xFFFF",
+    ]);
+
+    // Only the targets inside of the bytes are labelled
+    let labels: Vec<(u16, &str)> = lines
+        .iter()
+        .filter_map(|line| Some((line.address, line.label.as_deref()?)))
+        .collect();
+    assert_eq!(labels, [(0x200, "L200"), (0x20A, "L20A")]);
+    assert_eq!(lines[3].raw, [0x22, 0x0A]);
+}
+
+#[test]
+fn disassembly_assemble_back() {
+    let rom = [
+        0x6F, 0x05, // LD VF, 0x05
+        0x8F, 0xE4, // ADD VF, VE
+        0xFF, 0x1E, // ADD I, VF
+        0xDF, 0xA5, // DRW VF, VA, 5
+        0x22, 0x0A, // CALL 0x20A
+        0x8E, 0xF6, // SHR VE, VF
+        0x12, 0x00, // JP 0x200
+    ];
+
+    // The labels and the hexadecimal registers are read back by the assembler
+    let source: String = disasm::disassemble(&rom, 0x200)
+        .iter()
+        .map(|line| match &line.label {
+            Some(label) => format!("{}: {}\n", label, line.text),
+            None => format!("{}\n", line.text),
+        })
+        .collect();
+    assert!(source.contains("VF"));
+    assert_eq!(asm::assemble(&source).unwrap(), rom);
+}
+
+#[test]
+fn chip_key_conversions() {
+    use keypad::{ChipKey, InvalidKey};
+
+    // Every key value round trip
+    for value in 0..=0x0F {
+        let key = ChipKey::try_from(value).unwrap();
+        assert_eq!(u8::from(key), value);
+    }
+
+    assert!(matches!(ChipKey::try_from(0x10), Err(InvalidKey(0x10))));
+}
+
+#[test]
+fn draw_wrap_or_clip_edges() {
+    for wrap_sprites in [false, true] {
+        // The font zero F0 90 90 90 F0 across the bottom right corner
+        let config = ChipEmulatorConfig { wrap_sprites, ..Default::default() };
+        let mut emulator = emulator_with(config, &[
+            0xA0, 0x50, // LD I, 0x050
+            0x60, 0x3E, // LD V0, 62
+            0x61, 0x1E, // LD V1, 30
+            0xD0, 0x15, // DRW V0, V1, 5
+        ]);
+        run(&mut emulator, 4);
+        let frame = emulator.unpacked_frame();
+        let pixel = |x: usize, y: usize| frame[y * 64 + x];
+
+        // The part on screen is always drawn
+        assert_eq!((pixel(62, 30), pixel(63, 30), pixel(62, 31), pixel(63, 31)), (1, 1, 1, 0));
+
+        // The right and bottom overflow are wrapped or clipped
+        let wrapped = wrap_sprites as u8;
+        assert_eq!((pixel(0, 30), pixel(1, 30)), (wrapped, wrapped));
+        assert_eq!((pixel(62, 0), pixel(0, 2)), (wrapped, wrapped));
+        assert_eq!(frame.iter().map(|pixel| *pixel as u32).sum::<u32>(), if wrap_sprites { 14 } else { 3 });
+
+        // The starting coordinate always wrap
+        let config = ChipEmulatorConfig { wrap_sprites, ..Default::default() };
+        let mut emulator = emulator_with(config, &[
+            0xA0, 0x50, // LD I, 0x050
+            0x60, 0x42, // LD V0, 66
+            0x61, 0x21, // LD V1, 33
+            0xD0, 0x11, // DRW V0, V1, 1
+        ]);
+        run(&mut emulator, 4);
+        assert_eq!(emulator.unpacked_frame()[64 + 2..64 + 6], [1; 4]);
+    }
+}
+
+#[test]
+fn wait_key_registered_on_release() {
+    let mut emulator = emulator(&[
+        0x65, 0xFF, // LD V5, 0xFF
+        0xF5, 0x0A, // wait: LD V5, K
+        0x76, 0x01, // ADD V6, 1
+        0x12, 0x02, // JP wait
+    ]);
+    emulator.run_cycles(1).unwrap();
+
+    // The held key is not registered
+    emulator.set_key_bitmask(1 << 7);
+    emulator.run_cycles(10).unwrap();
+    assert_eq!(emulator.registers[5], 0xFF);
+    assert_eq!(emulator.program_counter, 0x202);
+
+    // The release register the key once, the next FX0A wait again
+    emulator.set_key_bitmask(0);
+    emulator.run_cycles(10).unwrap();
+    assert_eq!(emulator.registers[5], 7);
+    assert_eq!(emulator.registers[6], 1);
+}
+
+#[test]
+fn breakpoint_pause_before_instruction() {
+    let mut emulator = emulator(&[
+        0x60, 0x01, // LD V0, 1
+        0x61, 0x02, // LD V1, 2
+        0x72, 0x01, // main: ADD V2, 1
+        0x12, 0x04, // JP main
+    ]);
+    emulator.add_breakpoint(0x204);
+
+    // The execution stop at the main loop without running it
+    let run_until_break = |emulator: &mut ChipEmulator| {
+        (0..100).find_map(|_| match emulator.step_debug().unwrap() {
+            StepResult::Breakpoint(address) => Some(address),
+            StepResult::Executed => None,
+        })
+    };
+    assert_eq!(run_until_break(&mut emulator), Some(0x204));
+    assert_eq!(emulator.program_counter, 0x204);
+    assert_eq!(emulator.registers[..3], [1, 2, 0]);
+
+    // The next step run the instruction at the breakpoint
+    assert_eq!(emulator.step_debug(), Ok(StepResult::Executed));
+    assert_eq!(emulator.registers[2], 1);
+
+    // The breakpoint is hit again on the next loop, until removed
+    assert_eq!(run_until_break(&mut emulator), Some(0x204));
+    emulator.remove_breakpoint(0x204);
+    assert_eq!(run_until_break(&mut emulator), None);
+}
+
+#[test]
+fn replay_reproduce_recording() {
+    // Sum random values and count the cycles with the key 0 held
+    let rom = [
+        0xC0, 0xFF, // loop: RND V0, 0xFF
+        0x81, 0x04, // ADD V1, V0
+        0xE2, 0x9E, // SKP V2
+        0x12, 0x00, // JP loop
+        0x73, 0x01, // ADD V3, 1
+        0x12, 0x00, // JP loop
+    ];
+
+    let config = ChipEmulatorConfig { rng_seed: Some(42), ..Default::default() };
+    let mut recorded = ChipEmulator::initialize(config).unwrap();
+    recorded.load_rom_bytes(&rom).unwrap();
+    recorded.start_recording();
+
+    for keys in [0, 1, 0, 1] {
+        recorded.set_key_bitmask(keys);
+        recorded.run_cycles(25).unwrap();
+    }
+    let log = recorded.take_recording();
+    assert_eq!(log.rng_seed, Some(42));
+
+    // The replay use the seed of the log instead of its own
+    let config = ChipEmulatorConfig { rng_seed: Some(7), ..Default::default() };
+    let mut replayed = ChipEmulator::initialize(config).unwrap();
+    replayed.load_rom_bytes(&rom).unwrap();
+    replayed.load_replay(log);
+    replayed.run_cycles(100).unwrap();
+
+    assert_ne!(recorded.registers[3], 0);
+    assert_eq!(replayed.registers, recorded.registers);
+    assert_eq!(replayed.key_bitmask(), recorded.key_bitmask());
+    assert_eq!(replayed.framebuffer_hash(), recorded.framebuffer_hash());
+}
+
+#[test]
+fn initialize_reject_invalid_fonts() {
+    // A glyph using the low nibble is wider than the font
+    let mut font = fonts::DEFAULT_FONT;
+    font[5 * 3] = 0xFF;
+    let config = ChipEmulatorConfig { font, ..Default::default() };
+    assert!(matches!(ChipEmulator::initialize(config), Err(ChipError::InvalidFont { glyph: 3 })));
+
+    // An empty big font glyph
+    let mut big_font = fonts::DEFAULT_BIG_FONT;
+    big_font[10 * 9..10 * 10].fill(0);
+    let config = ChipEmulatorConfig { big_font: Some(big_font), ..Default::default() };
+    assert!(matches!(ChipEmulator::initialize(config), Err(ChipError::InvalidFont { glyph: 9 })));
+
+    assert!(ChipEmulator::initialize(ChipEmulatorConfig::default()).is_ok());
+}
+
+#[test]
+fn paused_emulator_frozen() {
+    // LD V0, 30; LD DT, V0; loop: ADD V1, 1; JP loop
+    let mut emulator = emulator(&[0x60, 0x1E, 0xF0, 0x15, 0x71, 0x01, 0x12, 0x04]);
+    emulator.run_cycles(3).unwrap();
+    emulator.set_paused(true);
+    assert!(emulator.is_paused());
+
+    // Neither the instructions nor the timers advance
+    for _ in 0..10 {
+        emulator.step().unwrap();
+        emulator.tick_timers();
+    }
+    emulator.run_frame(10).unwrap();
+    emulator.tick(100.).unwrap();
+    assert_eq!(emulator.program_counter(), 0x206);
+    assert_eq!(emulator.registers()[1], 1);
+    assert_eq!(emulator.delay_timer, 30);
+
+    // The resumed emulator continue from the same state
+    emulator.set_paused(false);
+    emulator.step().unwrap();
+    emulator.tick_timers();
+    assert_eq!(emulator.program_counter(), 0x204);
+    assert_eq!(emulator.delay_timer, 29);
+}
+
+#[test]
+fn error_halt_emulator() {
+    // RET with an empty stack
+    let mut emulator = emulator(&[0x00, 0xEE]);
+    assert!(!emulator.is_halted());
+
+    assert_eq!(emulator.step(), Err(ChipError::StackUnderflow));
+    assert!(emulator.is_halted());
+
+    // Every run method return the same error without running
+    let cycles = emulator.cycles;
+    assert_eq!(emulator.step(), Err(ChipError::StackUnderflow));
+    assert_eq!(emulator.run_cycles(10), Err(ChipError::StackUnderflow));
+    assert_eq!(emulator.tick(100.), Err(ChipError::StackUnderflow));
+    assert!(matches!(emulator.run_frame(10), Err(ChipError::StackUnderflow)));
+    assert_eq!(emulator.cycles, cycles);
+
+    // The reset machine can run again
+    emulator.reset_keep_rom();
+    assert!(!emulator.is_halted());
+    assert_eq!(emulator.step(), Err(ChipError::StackUnderflow));
+}
+
+#[test]
+fn reset_keep_rom() {
+    // LD V0, 20; LD DT, V0; LD ST, V0; LD I, 0x050; DRW V0, V0, 5
+    // LD I, 0x400; LD [I], V0; CALL 0x210; JP 0x210
+    let rom = [
+        0x60, 0x14, 0xF0, 0x15, 0xF0, 0x18, 0xA0, 0x50, 0xD0, 0x05,
+        0xA4, 0x00, 0xF0, 0x55, 0x22, 0x10, 0x12, 0x10,
+    ];
+    let mut emulator = emulator(&rom);
+    emulator.run_cycles(9).unwrap();
+    assert_eq!(emulator.memory_slice(0x400..0x401), Some(&[20][..]));
+
+    emulator.reset_keep_rom();
+
+    // The rom and the fonts are kept
+    assert_eq!(emulator.memory_slice(0x200..0x212), Some(&rom[..]));
+    assert_eq!(emulator.memory_slice(0x050..0x0A0), Some(&fonts::DEFAULT_FONT[..]));
+
+    // The machine state and the memory above the rom are cleared
+    assert_eq!(emulator.registers(), &[0; 16]);
+    assert_eq!((emulator.program_counter(), emulator.index_pointer()), (0x200, 0));
+    assert!(emulator.stack().is_empty());
+    assert_eq!((emulator.delay_timer, emulator.sound_timer), (0, 0));
+    assert!(emulator.unpacked_frame().iter().all(|pixel| *pixel == 0));
+    assert!(emulator.memory[0x212..].iter().all(|byte| *byte == 0));
+    assert!(emulator.get_video_buffer().1);
+}
+
+#[test]
+fn reset_power_on_state() {
+    // LD V0, 20; LD DT, V0; LD I, 0x050; DRW V0, V0, 5; loop: JP loop
+    let config = ChipEmulatorConfig::from_profile(CompatibilityProfile::Schip);
+    let mut emulator = emulator_with(config.clone(), &[0x60, 0x14, 0xF0, 0x15, 0xA0, 0x50, 0xD0, 0x05, 0x12, 0x08]);
+    emulator.run_cycles(5).unwrap();
+    emulator.add_breakpoint(0x208);
+    emulator.set_paused(true);
+
+    emulator.reset();
+
+    // The rom is unloaded and the machine match a new emulator
+    let fresh = emulator_with(config, &[]);
+    assert_eq!(emulator.snapshot(), fresh.snapshot());
+    assert_eq!(emulator.memory, fresh.memory);
+    assert!(!emulator.is_paused());
+    assert_eq!(emulator.step_debug(), Err(ChipError::UnknownOpcode([0x00, 0x00])));
+}
+
+#[test]
+fn reset_restart_recording() {
+    use replay::InputEvent;
+
+    // loop: RND V0, 0xFF; JP loop
+    let mut emulator = emulator(&[0xC0, 0xFF, 0x12, 0x00]);
+    emulator.start_recording();
+
+    emulator.run_cycles(10).unwrap();
+    let random = emulator.registers()[0];
+    emulator.set_key_bitmask(0b1);
+    emulator.run_cycles(10).unwrap();
+
+    // The reset machine replay the same random values
+    emulator.reset_keep_rom();
+    emulator.run_cycles(10).unwrap();
+    assert_eq!(emulator.registers()[0], random);
+    emulator.set_key_bitmask(0b10);
+
+    // Only the held keys and the events after the reset are kept
+    let log = emulator.take_recording();
+    assert_eq!(log.events, [InputEvent { cycle: 0, keys: 0b1 }, InputEvent { cycle: 10, keys: 0b10 }]);
+}
+
+#[test]
+fn state_bytes_round_trip() {
+    // LD V0, 7; LD DT, V0; LD I, 0x050; DRW V0, V0, 5; CALL 0x20A; JP 0x20A
+    let mut emulator = emulator(&[0x60, 0x07, 0xF0, 0x15, 0xA0, 0x50, 0xD0, 0x05, 0x22, 0x0A, 0x12, 0x0A]);
+    emulator.run_cycles(6).unwrap();
+    emulator.rpl_flags[3] = 9;
+    emulator.set_key_bitmask(0b100);
+    emulator.wait_key = Some(2);
+
+    let state = emulator.snapshot();
+    let bytes = state.to_bytes();
+    assert_eq!(ChipState::from_bytes(&bytes), Ok(state.clone()));
+
+    // The restored emulator capture the same state
+    let mut restored = emulator_with(ChipEmulatorConfig::default(), &[]);
+    restored.restore(&state).unwrap();
+    assert_eq!(restored.snapshot(), state);
+}
+
+#[test]
+fn state_bytes_reject_invalid_input() {
+    // loop: JP loop
+    let state = emulator(&[0x12, 0x00]).snapshot();
+    let bytes = state.to_bytes();
+
+    // Every truncation and trailing bytes are rejected
+    for len in 0..bytes.len() {
+        assert_eq!(ChipState::from_bytes(&bytes[..len]), Err(ChipError::InvalidState));
+    }
+    assert_eq!(ChipState::from_bytes(&[&bytes[..], &[0]].concat()), Err(ChipError::InvalidState));
+
+    // A memory size outside of the supported range is rejected
+    let mut corrupt = bytes.clone();
+    corrupt[5..9].copy_from_slice(&0u32.to_le_bytes());
+    assert_eq!(ChipState::from_bytes(&corrupt), Err(ChipError::InvalidState));
+
+    // A state of another memory size or with a stack deeper than
+    // max_stack_depth leave the emulator untouched
+    let config = ChipEmulatorConfig::from_profile(CompatibilityProfile::XoChip);
+    let mut xo_chip = emulator_with(config, &[0x60, 0x01]);
+    let before = xo_chip.snapshot();
+    assert_eq!(xo_chip.restore(&state), Err(ChipError::InvalidState));
+    assert_eq!(xo_chip.snapshot(), before);
+
+    let deep = ChipState { stack: vec![0x200; 17], ..state.clone() };
+    assert_eq!(ChipState::from_bytes(&deep.to_bytes()), Ok(deep.clone()));
+    let mut emulator = emulator(&[0x12, 0x00]);
+    assert_eq!(emulator.restore(&deep), Err(ChipError::InvalidState));
+    assert_eq!(emulator.snapshot(), state);
+}
+
+#[test]
+fn xo_chip_strict_memory_ranges() {
+    let profile = ChipEmulatorConfig::from_profile(CompatibilityProfile::XoChip);
+    let config = ChipEmulatorConfig { memory_size: 0x1000, ..profile };
+    let strict = ChipEmulatorConfig { strict: true, ..config.clone() };
+
+    // LD I, 0xFF8 followed by an access of 16 bytes
+    for opcode in [[0x50, 0xF2], [0x50, 0xF3], [0xF0, 0x02]] {
+        let rom = [0xAF, 0xF8, opcode[0], opcode[1]];
+
+        let mut emulator = emulator_with(config.clone(), &rom);
+        run(&mut emulator, 2);
+
+        let mut emulator = emulator_with(strict.clone(), &rom);
+        emulator.step().unwrap();
+        assert_eq!(emulator.step(), Err(ChipError::MemoryOutOfBounds { address: 0x1000 }));
+    }
+
+    // F000 NNNN with its address word past the end of memory
+    let mut emulator = emulator_with(strict, &[]);
+    emulator.memory[0xFFE..].copy_from_slice(&[0xF0, 0x00]);
+    emulator.program_counter = 0xFFE;
+    assert_eq!(emulator.step(), Err(ChipError::MemoryOutOfBounds { address: 0x1000 }));
+}
+
+#[test]
+fn measured_ips_per_emulated_second() {
+    let config = ChipEmulatorConfig { instruction_per_second: 600, ..Default::default() };
+    // loop: JP loop
+    let mut emulator = emulator_with(config, &[0x12, 0x00]);
+
+    // Nothing is measured before the first tick, a partial window is extrapolated
+    assert_eq!(emulator.measured_ips(), 0.);
+    for _ in 0..30 {
+        emulator.run_frame(10).unwrap();
+    }
+    assert_eq!(emulator.measured_ips(), 600.);
+
+    // Every instruction running path is counted
+    for _ in 0..30 {
+        emulator.run_frame(10).unwrap();
+    }
+    assert_eq!(emulator.measured_ips(), 600.);
+
+    // The window slide by one tick at a time
+    for _ in 0..30 {
+        emulator.run_frame(5).unwrap();
+    }
+    assert_eq!(emulator.measured_ips(), 450.);
+    for _ in 0..30 {
+        emulator.run_frame(10).unwrap();
+    }
+    assert_eq!(emulator.measured_ips(), 450.);
+
+    for _ in 0..60 {
+        emulator.tick(1000. / 60.).unwrap();
+    }
+    assert!((emulator.measured_ips() - 600.).abs() <= 10.);
+
+    for _ in 0..60 {
+        emulator.run_cycles(5).unwrap();
+        emulator.tick_timers();
+    }
+    assert_eq!(emulator.measured_ips(), 300.);
+}
+
+#[test]
+fn shutdown_write_recording() {
+    use replay::{InputEvent, InputLog};
+
+    let path = std::env::temp_dir().join("chip8rs-test-shutdown-recording.txt");
+    // loop: JP loop
+    let mut emulator = emulator(&[0x12, 0x00]);
+
+    emulator.start_recording_to(&path);
+    emulator.run_cycles(3).unwrap();
+    emulator.set_key_bitmask(0b100);
+    emulator.run_cycles(2).unwrap();
+    emulator.shutdown().unwrap();
+
+    // The recording was flushed and is no longer in progress
+    let log = InputLog::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(log.rng_seed, Some(0));
+    assert_eq!(log.events, [InputEvent { cycle: 0, keys: 0 }, InputEvent { cycle: 3, keys: 0b100 }]);
+    assert!(emulator.take_recording().events.is_empty());
+}
+
+#[test]
+fn left_rom_event_with_max_size_rom() {
+    use std::{cell::RefCell, rc::Rc};
+
+    // ADD V0, 0 filling the whole 64 KiB memory after the rom start address
+    let config = ChipEmulatorConfig::from_profile(CompatibilityProfile::XoChip);
+    let mut emulator = emulator_with(config, &[0x70, 0x00].repeat(0x7F00));
+    assert_eq!(emulator.rom_range(), 0x200..0x10000);
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let hook_events = Rc::clone(&events);
+    emulator.set_event_hook(Box::new(move |event| hook_events.borrow_mut().push(event)));
+
+    // The last words are inside of the rom, the program counter then wrap to 0
+    emulator.program_counter = 0xFFFC;
+    run(&mut emulator, 2);
+    assert!(events.borrow().is_empty());
+
+    assert_eq!(emulator.step(), Err(ChipError::UnknownOpcode([0x00, 0x00])));
+    assert_eq!(*events.borrow(), [ChipEvent::LeftRom { address: 0x000 }]);
+}
+
+#[test]
+fn logic_vf_behavior() {
+    // LD V0, 0x0C; LD V1, 0x0A; LD VF, 0x07 followed by OR, AND or XOR V0, V1
+    for (opcode, result) in [(0x11, 0x0E), (0x12, 0x08), (0x13, 0x06)] {
+        let rom = [0x60, 0x0C, 0x61, 0x0A, 0x6F, 0x07, 0x80, opcode];
+
+        for (logic_vf, vf) in [(LogicVfBehavior::Unchanged, 0x07), (LogicVfBehavior::ResetToZero, 0)] {
+            let config = ChipEmulatorConfig { logic_vf, ..Default::default() };
+            let mut emulator = emulator_with(config, &rom);
+            run(&mut emulator, 4);
+
+            assert_eq!(emulator.registers[0x0], result, "8XY{:X}", opcode & 0xF);
+            assert_eq!(emulator.registers[0xF], vf, "8XY{:X} {:?}", opcode & 0xF, logic_vf);
+        }
+    }
+}
+
+#[test]
+fn execute_opcode_policy_and_program_counter() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let config = ChipEmulatorConfig {
+        instruction_set: InstructionSet::SuperChip,
+        unknown_opcode_policy: UnknownOpcodePolicy::Event,
+        ..Default::default()
+    };
+    let mut emulator = emulator_with(config, &[]);
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let hook_events = Rc::clone(&events);
+    emulator.set_event_hook(Box::new(move |event| hook_events.borrow_mut().push(event)));
+
+    // ADD V1, V2 run against the registers without moving the program counter
+    emulator.registers[0x1] = 0x20;
+    emulator.registers[0x2] = 0x22;
+    emulator.execute_opcode([0x81, 0x24]).unwrap();
+    assert_eq!(emulator.registers[0x1], 0x42);
+    assert_eq!(emulator.program_counter, 0x200);
+
+    // Unknown opcodes follow the unknown opcode policy
+    emulator.execute_opcode([0xFF, 0xFF]).unwrap();
+    assert_eq!(*events.borrow(), [ChipEvent::UnknownOpcode { address: 0x200, opcode: 0xFFFF }]);
+    events.borrow_mut().clear();
+
+    // The exit halt the emulator on the current program counter
+    emulator.execute_opcode([0x00, 0xFD]).unwrap();
+    assert!(emulator.is_halted());
+    assert_eq!(emulator.program_counter, 0x200);
+    assert_eq!(*events.borrow(), [ChipEvent::Exit { address: 0x200 }]);
+}
@@ -0,0 +1,86 @@
+use crate::display::{HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH};
+
+/// Number of pixels in the high resolution mode
+const PIXEL_COUNT: usize = (HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT) as usize;
+
+/// Video buffer storing either one byte or one bit per pixel
+/// Sized for the high resolution mode, the callers only use the
+/// pixels of the current resolution
+#[derive(Clone)]
+pub(crate) struct VideoBuffer {
+    /// One byte per pixel, or eight pixels per byte
+    /// with the leftmost pixel in the most significant bit
+    data: Vec<u8>,
+    packed: bool,
+}
+
+impl VideoBuffer {
+    /// Create a cleared video buffer
+    pub(crate) fn new(packed: bool) -> Self {
+        let len = if packed { PIXEL_COUNT / 8 } else { PIXEL_COUNT };
+
+        Self { data: vec![0; len], packed }
+    }
+
+    /// Return the value of the pixel at the given index
+    pub(crate) fn get(&self, index: usize) -> u8 {
+        if self.packed {
+            (self.data[index / 8] >> (7 - index % 8)) & 1
+        } else {
+            self.data[index]
+        }
+    }
+
+    /// Set the value of the pixel at the given index
+    pub(crate) fn set(&mut self, index: usize, value: u8) {
+        if self.packed {
+            let mask = 1 << (7 - index % 8);
+
+            if value != 0 {
+                self.data[index / 8] |= mask;
+            } else {
+                self.data[index / 8] &= !mask;
+            }
+        } else {
+            self.data[index] = value;
+        }
+    }
+
+    /// Return the raw storage of the bytes buffer, None if packed
+    pub(crate) fn bytes_mut(&mut self) -> Option<&mut [u8]> {
+        if self.packed {
+            None
+        } else {
+            Some(&mut self.data)
+        }
+    }
+
+    /// Return the raw storage of the first len pixels
+    pub(crate) fn raw(&self, len: usize) -> &[u8] {
+        if self.packed {
+            &self.data[..len.div_ceil(8)]
+        } else {
+            &self.data[..len]
+        }
+    }
+
+    /// Return the number of bytes of the storage
+    pub(crate) fn footprint(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return an iterator over the first len pixels, one byte per pixel
+    pub(crate) fn pixels(&self, len: usize) -> impl Iterator<Item = u8> + '_ {
+        (0..len).map(|index| self.get(index))
+    }
+
+    /// Turn off the first len pixels
+    pub(crate) fn clear(&mut self, len: usize) {
+        if self.packed {
+            // The screen sizes are multiple of 8, no partial byte to clear
+            self.data[..len.div_ceil(8)].fill(0);
+        } else {
+            self.data[..len].fill(0);
+        }
+    }
+}